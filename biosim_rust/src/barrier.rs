@@ -0,0 +1,145 @@
+use rand::Rng;
+use rand::seq::index;
+
+use crate::grid::OccupancyGrid;
+use crate::vector2d::Vector2D;
+
+
+// Shapes of barrier cells placed on the field, before the first generation spawns
+// Parts of a shape outside the field are ignored
+#[derive(Debug, Clone, PartialEq)]
+pub enum Barrier {
+    // Straight (or diagonal) line between both ends, inclusive
+    Line { start: Vector2D<usize>, end: Vector2D<usize> },
+    // Filled rectangle, with `corner` being its north-west corner
+    Rectangle { corner: Vector2D<usize>, width: usize, height: usize },
+    // `total_cells` barrier cells at random positions
+    RandomScatter { total_cells: usize },
+    // Any set of cells, e.g. from an ArenaMap
+    Cells(Vec<Vector2D<usize>>),
+}
+
+impl Barrier {
+    pub fn place<R: Rng>(&self, grid: &mut OccupancyGrid, rng: &mut R) {
+        match *self {
+            Barrier::Line { start, end } => {
+                for pos in line_cells(start, end) {
+                    grid.set_barrier(&pos);
+                }
+            }
+
+            Barrier::Rectangle { corner, width, height } => {
+                for y in corner.y..corner.y.saturating_add(height) {
+                    for x in corner.x..corner.x.saturating_add(width) {
+                        grid.set_barrier(&Vector2D::new(x, y));
+                    }
+                }
+            }
+
+            Barrier::RandomScatter { total_cells } => {
+                // Only free cells, so overlapping shapes don't eat up the scatter
+                let free_cells: Vec<usize> = (0..grid.width() * grid.height())
+                    .filter(|&i| grid.is_occupied(&grid.index_to_position(i)) == Some(false))
+                    .collect();
+                let total_cells = total_cells.min(free_cells.len());
+
+                for i in index::sample(rng, free_cells.len(), total_cells) {
+                    grid.set_barrier(&grid.index_to_position(free_cells[i]));
+                }
+            }
+
+            Barrier::Cells(ref cells) => {
+                for pos in cells {
+                    grid.set_barrier(pos);
+                }
+            }
+        }
+    }
+}
+
+// Bresenham's line algorithm
+fn line_cells(start: Vector2D<usize>, end: Vector2D<usize>) -> Vec<Vector2D<usize>> {
+    let (x0, y0) = (start.x as isize, start.y as isize);
+    let (x1, y1) = (end.x as isize, end.y as isize);
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+
+    let mut cells = vec![];
+    let (mut x, mut y) = (x0, y0);
+    let mut error = dx + dy;
+    loop {
+        cells.push(Vector2D::new(x as usize, y as usize));
+        if x == x1 && y == y1 {
+            break;
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+
+    cells
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    use super::*;
+
+    #[test]
+    fn place_lines_and_rectangles() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        let mut grid = OccupancyGrid::new(10, 10);
+
+        Barrier::Line { start: Vector2D::new(6, 0), end: Vector2D::new(6, 4) }.place(&mut grid, &mut rng);
+        Barrier::Line { start: Vector2D::new(3, 3), end: Vector2D::new(0, 0) }.place(&mut grid, &mut rng);
+        assert_eq!(grid.total_barriers(), 5 + 4);
+        assert!(grid.is_barrier(&Vector2D::new(6, 2)));
+        assert!(grid.is_barrier(&Vector2D::new(1, 1)));
+
+        // Hangs over the field's edges
+        Barrier::Rectangle { corner: Vector2D::new(5, 8), width: 10, height: 3 }.place(&mut grid, &mut rng);
+        assert_eq!(grid.total_barriers(), 9 + 5 * 2);
+        assert!(grid.is_barrier(&Vector2D::new(9, 9)));
+        assert!(!grid.is_barrier(&Vector2D::new(4, 9)));
+    }
+
+    #[test]
+    fn line_through_every_octant() {
+        let center = Vector2D::new(5, 5);
+        for end in [(9, 7), (7, 9), (3, 9), (1, 7), (1, 3), (3, 1), (7, 1), (9, 3)] {
+            let cells = line_cells(center, Vector2D::new(end.0, end.1));
+
+            assert_eq!(cells.first(), Some(&center));
+            assert_eq!(cells.last(), Some(&Vector2D::new(end.0, end.1)));
+            // Every cell touches the previous one
+            for pair in cells.windows(2) {
+                assert!(pair[0].x.abs_diff(pair[1].x) <= 1 && pair[0].y.abs_diff(pair[1].y) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn scatter_on_free_cells() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        let mut grid = OccupancyGrid::new(5, 5);
+
+        Barrier::Rectangle { corner: Vector2D::new(0, 0), width: 5, height: 4 }.place(&mut grid, &mut rng);
+        Barrier::RandomScatter { total_cells: 3 }.place(&mut grid, &mut rng);
+        assert_eq!(grid.total_barriers(), 20 + 3);
+
+        // Can't scatter more than what's left
+        Barrier::RandomScatter { total_cells: 10 }.place(&mut grid, &mut rng);
+        assert_eq!(grid.total_barriers(), 25);
+    }
+}
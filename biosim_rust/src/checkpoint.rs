@@ -0,0 +1,216 @@
+use std::io::{self, Read, Write};
+
+use rand::{RngCore, SeedableRng};
+use rand_pcg::Pcg64;
+use thiserror::Error;
+
+use crate::simulation::RngSeed;
+
+// Checkpoint file layout (every number is little-endian):
+// - MAGIC, then CHECKPOINT_VERSION as u32
+// - Simulation state (see `Simulation::save_checkpoint`)
+// - Every creature (see `Creature::write_checkpoint`)
+// Bump CHECKPOINT_VERSION whenever the layout changes
+pub const MAGIC: &[u8; 8] = b"BIOSIMCP";
+pub const CHECKPOINT_VERSION: u32 = 3;
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("Couldn't read/write checkpoint: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a checkpoint file")]
+    BadMagic,
+    #[error("Unsupported checkpoint version {0} (expected {1})")]
+    UnsupportedVersion(u32, u32),
+    #[error("Checkpoint field is {0}x{1}, but the config expects {2}x{3}")]
+    FieldMismatch(usize, usize, usize, usize),
+    #[error("Checkpoint has {0} internal neurons per creature, but the config expects {1}")]
+    InternalNeuronMismatch(usize, usize),
+    #[error("Invalid neuron ID {0} in checkpoint")]
+    InvalidNeuronID(usize),
+    #[error("Creature #{0} is outside the field, or on an occupied cell")]
+    InvalidPosition(usize),
+    #[error("Invalid creature in checkpoint: {0}")]
+    InvalidCreature(String),
+}
+
+// Pcg64 doesn't expose its state, but it's trivial to fast-forward
+// So we keep the seed, and count how many times the Rng has been stepped
+pub struct TrackedPcg64 {
+    rng: Pcg64,
+    seed: RngSeed,
+    total_draws: u128
+}
+
+impl TrackedPcg64 {
+    pub fn from_seed(seed: RngSeed) -> Self {
+        Self {
+            rng: Pcg64::from_seed(seed),
+            seed,
+            total_draws: 0
+        }
+    }
+
+    pub fn from_state(seed: RngSeed, total_draws: u128) -> Self {
+        let mut rng = Pcg64::from_seed(seed);
+        rng.advance(total_draws);
+
+        Self { rng, seed, total_draws }
+    }
+
+    pub fn seed(&self) -> RngSeed {
+        self.seed
+    }
+
+    pub fn total_draws(&self) -> u128 {
+        self.total_draws
+    }
+}
+
+// Each output (u32 or u64) steps Pcg64 exactly once
+// Bytes are filled 8 at a time, with a partial chunk at the end
+impl RngCore for TrackedPcg64 {
+    fn next_u32(&mut self) -> u32 {
+        self.total_draws += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.total_draws += 1;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.total_draws += dest.len().div_ceil(8) as u128;
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+
+pub fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_u32(writer, CHECKPOINT_VERSION)
+}
+
+pub fn read_header<R: Read>(reader: &mut R) -> Result<(), CheckpointError> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(CheckpointError::BadMagic);
+    }
+
+    let version = read_u32(reader)?;
+    if version != CHECKPOINT_VERSION {
+        return Err(CheckpointError::UnsupportedVersion(version, CHECKPOINT_VERSION));
+    }
+
+    Ok(())
+}
+
+pub fn write_u16<W: Write>(writer: &mut W, value: u16) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u128<W: Write>(writer: &mut W, value: u128) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+// usize is always stored as u64, so checkpoints are portable
+pub fn write_usize<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    write_u64(writer, value as u64)
+}
+
+pub fn write_f64<W: Write>(writer: &mut W, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(read_bytes(reader)?))
+}
+
+pub fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+pub fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+pub fn read_u128<R: Read>(reader: &mut R) -> io::Result<u128> {
+    Ok(u128::from_le_bytes(read_bytes(reader)?))
+}
+
+pub fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    Ok(read_u64(reader)? as usize)
+}
+
+pub fn read_f64<R: Read>(reader: &mut R) -> io::Result<f64> {
+    Ok(f64::from_le_bytes(read_bytes(reader)?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracked_rng_matches_pcg64() {
+        let seed = [9; 32];
+        let mut tracked = TrackedPcg64::from_seed(seed);
+        let mut plain = Pcg64::from_seed(seed);
+
+        let (mut a, mut b) = ([0; 13], [0; 13]);
+        tracked.fill_bytes(&mut a);
+        plain.fill_bytes(&mut b);
+        assert_eq!(a, b);
+        assert_eq!(tracked.next_u32(), plain.next_u32());
+        assert_eq!(tracked.next_u64(), plain.next_u64());
+        assert_eq!(tracked.total_draws(), 4);
+    }
+
+    #[test]
+    fn restore_tracked_rng() {
+        let mut rng = TrackedPcg64::from_seed([4; 32]);
+        for _ in 0..1000 {
+            rng.next_u32();
+        }
+
+        let mut restored = TrackedPcg64::from_state(rng.seed(), rng.total_draws());
+        for _ in 0..100 {
+            assert_eq!(rng.next_u64(), restored.next_u64());
+        }
+    }
+
+    #[test]
+    fn reject_bad_header() {
+        let mut bytes = vec![];
+        write_header(&mut bytes).unwrap();
+        assert!(read_header(&mut bytes.as_slice()).is_ok());
+
+        bytes[8] += 1;
+        assert!(matches!(
+            read_header(&mut bytes.as_slice()),
+            Err(CheckpointError::UnsupportedVersion(found, CHECKPOINT_VERSION)) if found == CHECKPOINT_VERSION + 1
+        ));
+        assert!(matches!(read_header(&mut &b"NOTACKPT\x01\x00\x00\x00"[..]), Err(CheckpointError::BadMagic)));
+    }
+}
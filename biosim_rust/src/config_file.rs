@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::barrier::Barrier;
+use crate::genome::{Crossover, LengthMutation, PointMutation};
+use crate::map_file::{load_map, MapFileError};
+use crate::neuron::GeneFormat;
+use crate::neuron::internal_neuron::Activation;
+use crate::renderer::Color;
+use crate::reproduction::{Pairing, Reproduction};
+use crate::selection::{Circle, Corners, Everyone, HalfField, NearWall, SelectionCriterion, Side};
+use crate::simulation::{MovementConflict, SimulationConfig, SimulationConfigBuilder, SimulationConfigError};
+use crate::topology::Topology;
+use crate::vector2d::Vector2D;
+
+// INI-like config file format:
+//
+// # Comments start with '#'
+// [simulation]
+// field_width = 50
+// field_height = 50
+// population = 300
+//
+// Every key belongs to a section, and is referred to as `section.key` in error messages
+// Missing keys fall back to the defaults below
+const KNOWN_KEYS: &[&str] = &[
+    "simulation.field_width",
+    "simulation.field_height",
+    "simulation.population",
+    "simulation.seed",
+    "simulation.total_genes",
+    "simulation.steps_per_generation",
+    "simulation.movement_conflict",
+    "simulation.topology",
+    "simulation.internal_neurons",
+    "simulation.activation",
+    "simulation.leaky_relu_slope",
+    "simulation.neuron_decay",
+    "simulation.gene_format",
+    "simulation.population_file",
+
+    "mutation.mode",
+    "mutation.rate",
+    "mutation.insertion_rate",
+    "mutation.deletion_rate",
+    "mutation.min_genes",
+    "mutation.max_genes",
+
+    "reproduction.mode",
+    "reproduction.crossover",
+    "reproduction.pairing",
+
+    "selection.criterion",
+    "selection.side",
+    "selection.center_x",
+    "selection.center_y",
+    "selection.radius",
+    "selection.distance",
+    "selection.size",
+    "selection.invert",
+
+    "barriers.lines",
+    "barriers.rectangles",
+    "barriers.random",
+
+    "map.file",
+
+    "renderer.field_color",
+    "renderer.border_color",
+    "renderer.barrier_color",
+
+    "output.directory",
+    "output.generations",
+    "output.rendered_steps",
+
+    "checkpoint.load",
+    "checkpoint.save",
+];
+
+const DEFAULT_FIELD_WIDTH: usize = 50;
+const DEFAULT_FIELD_HEIGHT: usize = 50;
+const DEFAULT_POPULATION: usize = 300;
+const DEFAULT_TOTAL_GENES: usize = 8;
+const DEFAULT_FIELD_COLOR: u32 = 0xffdd8c;
+const DEFAULT_BORDER_COLOR: u32 = 0xaaaaaa;
+const DEFAULT_OUTPUT_DIR: &str = "./output";
+const DEFAULT_RENDERED_STEPS: usize = 20;
+
+// Everything needed for one run of the binary
+pub struct RunConfig {
+    pub simulation: SimulationConfig,
+    pub field_color: Color,
+    pub border_color: Color,
+    pub barrier_color: Option<Color>,
+    pub output_dir: PathBuf,
+    // Generations to evolve before rendering
+    pub total_generations: usize,
+    // Steps rendered (one TGA each) after evolving
+    pub rendered_steps: usize,
+    // Spawn the first generation from this population file instead of random genes
+    pub population_file: Option<PathBuf>,
+    // Resume from this checkpoint instead of spawning a fresh population
+    pub load_checkpoint: Option<PathBuf>,
+    // Save a checkpoint here after evolving
+    pub save_checkpoint: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    #[error("Couldn't read config file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {0}: expected `[section]` or `key = value`")]
+    MalformedLine(usize),
+    #[error("Line {0}: `{1}` is outside of any [section]")]
+    KeyWithoutSection(usize, String),
+    #[error("Line {0}: unknown key `{1}`")]
+    UnknownKey(usize, String),
+    #[error("Line {0}: `{1}` is set more than once")]
+    DuplicateKey(usize, String),
+    #[error("Line {0}: invalid value `{2}` for `{1}` (expected {3})")]
+    InvalidValue(usize, String, String, &'static str),
+    #[error("`{0}` is required when `{1}` is set")]
+    MissingKey(&'static str, &'static str),
+    #[error("Invalid value for `{0}`: {1}")]
+    InvalidConfig(&'static str, SimulationConfigError),
+    #[error("Couldn't load `map.file`: {0}")]
+    Map(#[from] MapFileError),
+}
+
+// Raw `section.key` => (line number, value)
+struct Entries(HashMap<String, (usize, String)>);
+
+impl Entries {
+    fn parse(text: &str) -> Result<Self, ConfigFileError> {
+        let mut entries = HashMap::new();
+        let mut section: Option<String> = None;
+
+        for (i, raw_line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = raw_line.split('#').next().unwrap().trim();
+            if line.is_empty() { continue }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+                section = Some(name.trim().to_string());
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or(ConfigFileError::MalformedLine(line_number))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty() {
+                return Err(ConfigFileError::MalformedLine(line_number));
+            }
+
+            let full_key = match &section {
+                Some(section) => format!("{}.{}", section, key),
+                None => return Err(ConfigFileError::KeyWithoutSection(line_number, key.to_string()))
+            };
+
+            if !KNOWN_KEYS.contains(&full_key.as_str()) {
+                return Err(ConfigFileError::UnknownKey(line_number, full_key));
+            }
+
+            if entries.contains_key(&full_key) {
+                return Err(ConfigFileError::DuplicateKey(line_number, full_key));
+            }
+
+            entries.insert(full_key, (line_number, value.to_string()));
+        }
+
+        Ok(Self(entries))
+    }
+
+    fn is_set(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    // Parse `key` with `parser`; `expected` describes valid values for the error message
+    fn get_with<T>(&self, key: &str, expected: &'static str, parser: impl Fn(&str) -> Option<T>) -> Result<Option<T>, ConfigFileError> {
+        match self.0.get(key) {
+            None => Ok(None),
+            Some((line, value)) => parser(value)
+                .map(Some)
+                .ok_or_else(|| ConfigFileError::InvalidValue(*line, key.to_string(), value.clone(), expected))
+        }
+    }
+
+    fn get<T: FromStr>(&self, key: &str, expected: &'static str) -> Result<Option<T>, ConfigFileError> {
+        self.get_with(key, expected, |value| value.parse().ok())
+    }
+
+    fn get_or<T: FromStr>(&self, key: &str, expected: &'static str, default: T) -> Result<T, ConfigFileError> {
+        Ok(self.get(key, expected)?.unwrap_or(default))
+    }
+
+    fn require<T: FromStr>(&self, key: &'static str, expected: &'static str, needed_by: &'static str) -> Result<T, ConfigFileError> {
+        self.get(key, expected)?.ok_or(ConfigFileError::MissingKey(key, needed_by))
+    }
+}
+
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<RunConfig, ConfigFileError> {
+    parse_config(&fs::read_to_string(path)?)
+}
+
+pub fn parse_config(text: &str) -> Result<RunConfig, ConfigFileError> {
+    let entries = Entries::parse(text)?;
+
+    let seed = entries.get_or::<u64>("simulation.seed", "an unsigned integer", 0)?;
+    let mut rng_seed = [0; 32];
+    rng_seed[..8].copy_from_slice(&seed.to_le_bytes());
+
+    let mut builder = SimulationConfigBuilder::new()
+        .with_field_dimensions(
+            entries.get_or("simulation.field_width", "an unsigned integer", DEFAULT_FIELD_WIDTH)?,
+            entries.get_or("simulation.field_height", "an unsigned integer", DEFAULT_FIELD_HEIGHT)?
+        )
+        .with_population(entries.get_or("simulation.population", "an unsigned integer", DEFAULT_POPULATION)?)
+        .with_seed(rng_seed)
+        .with_total_genes(entries.get_or("simulation.total_genes", "an unsigned integer", DEFAULT_TOTAL_GENES)?)
+        .with_point_mutation(parse_point_mutation(&entries)?)
+        .with_reproduction(parse_reproduction(&entries)?);
+
+    // The map's size wins over field_width/field_height, and its safe zone (if any)
+    // is the selection criterion, unless `selection.criterion` is set
+    let map = entries.get::<PathBuf>("map.file", "a path")?.map(load_map).transpose()?;
+    let map_has_safe_zone = map.as_ref().is_some_and(|map| !map.safe_zone.is_empty());
+    if let Some(map) = map {
+        builder = builder.with_arena_map(map);
+    }
+
+    if !map_has_safe_zone || entries.is_set("selection.criterion") {
+        builder = builder.with_selection(parse_selection(&entries)?);
+    }
+
+    for barrier in parse_barriers(&entries)? {
+        builder = builder.with_barrier(barrier);
+    }
+
+    if let Some(steps) = entries.get("simulation.steps_per_generation", "an unsigned integer")? {
+        builder = builder.with_steps_per_generation(steps);
+    }
+
+    if let Some(movement_conflict) = entries.get_with(
+        "simulation.movement_conflict", "first_come, random_winner or all_blocked",
+        |value| match value {
+            "first_come" => Some(MovementConflict::FirstCome),
+            "random_winner" => Some(MovementConflict::RandomWinner),
+            "all_blocked" => Some(MovementConflict::AllBlocked),
+            _ => None
+        }
+    )? {
+        builder = builder.with_movement_conflict(movement_conflict);
+    }
+
+    if let Some(topology) = entries.get_with(
+        "simulation.topology", "walled, toroidal or reflecting",
+        |value| match value {
+            "walled" => Some(Topology::Walled),
+            "toroidal" => Some(Topology::Toroidal),
+            "reflecting" => Some(Topology::Reflecting),
+            _ => None
+        }
+    )? {
+        builder = builder.with_topology(topology);
+    }
+
+    if let Some(total) = entries.get("simulation.internal_neurons", "an unsigned integer")? {
+        builder = builder.with_internal_neurons(total);
+    }
+
+    // None stands for `genome`
+    if let Some(activation) = entries.get_with(
+        "simulation.activation", "tanh, sigmoid, relu, leaky_relu, identity, step or genome",
+        |value| match value {
+            "tanh" => Some(Some(Activation::Tanh)),
+            "sigmoid" => Some(Some(Activation::Sigmoid)),
+            "relu" => Some(Some(Activation::Relu)),
+            "leaky_relu" => Some(Some(Activation::LeakyRelu)),
+            "identity" => Some(Some(Activation::Identity)),
+            "step" => Some(Some(Activation::Step)),
+            "genome" => Some(None),
+            _ => None
+        }
+    )? {
+        builder = match activation {
+            Some(activation) => builder.with_activation(activation),
+            None => builder.with_activation_from_genome()
+        };
+    }
+
+    if let Some(slope) = entries.get("simulation.leaky_relu_slope", "a number")? {
+        builder = builder.with_leaky_relu_slope(slope);
+    }
+
+    if let Some(gene_format) = entries.get_with("simulation.gene_format", "narrow or wide", |value| match value {
+        "narrow" => Some(GeneFormat::Narrow),
+        "wide" => Some(GeneFormat::Wide),
+        _ => None
+    })? {
+        builder = builder.with_gene_format(gene_format);
+    }
+
+    if let Some(decay) = entries.get("simulation.neuron_decay", "a number")? {
+        builder = builder.with_neuron_decay(decay);
+    }
+
+    if let Some(length_mutation) = parse_length_mutation(&entries)? {
+        builder = builder.with_length_mutation(length_mutation);
+    }
+
+    let simulation = builder.build()
+        .map_err(|err| ConfigFileError::InvalidConfig(offending_key(&err), err))?;
+
+    Ok(RunConfig {
+        simulation,
+        field_color: entries.get_with("renderer.field_color", "a RRGGBB hex color", parse_color)?
+            .unwrap_or(Color::from_xrgb_u32(DEFAULT_FIELD_COLOR)),
+        border_color: entries.get_with("renderer.border_color", "a RRGGBB hex color", parse_color)?
+            .unwrap_or(Color::from_xrgb_u32(DEFAULT_BORDER_COLOR)),
+        barrier_color: entries.get_with("renderer.barrier_color", "a RRGGBB hex color", parse_color)?,
+        output_dir: entries.get_or("output.directory", "a path", PathBuf::from(DEFAULT_OUTPUT_DIR))?,
+        total_generations: entries.get_or("output.generations", "an unsigned integer", 0)?,
+        rendered_steps: entries.get_or("output.rendered_steps", "an unsigned integer", DEFAULT_RENDERED_STEPS)?,
+        population_file: entries.get("simulation.population_file", "a path")?,
+        load_checkpoint: entries.get("checkpoint.load", "a path")?,
+        save_checkpoint: entries.get("checkpoint.save", "a path")?,
+    })
+}
+
+// Which key (or keys) a validation error is about
+fn offending_key(err: &SimulationConfigError) -> &'static str {
+    match err {
+        SimulationConfigError::FieldTooSmall(..) => "simulation.field_width/field_height",
+        SimulationConfigError::PopulationExceedsField(..) => "simulation.population",
+        SimulationConfigError::NoGenes => "simulation.total_genes",
+        SimulationConfigError::NoSteps => "simulation.steps_per_generation",
+        SimulationConfigError::InvalidMutationRate(..) => "mutation.rate/insertion_rate/deletion_rate",
+        SimulationConfigError::InvalidGenomeLength(..) => "mutation.min_genes/max_genes",
+        SimulationConfigError::NoInternalNeurons
+            | SimulationConfigError::TooManyInternalNeurons(..) => "simulation.internal_neurons",
+        SimulationConfigError::InvalidNeuronDecay(..) => "simulation.neuron_decay",
+        SimulationConfigError::SpawnRegionFull(..) => "map.file",
+    }
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    // from_str_radix would also take a leading `+`
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16).ok().map(Color::from_xrgb_u32)
+}
+
+fn parse_point_mutation(entries: &Entries) -> Result<PointMutation, ConfigFileError> {
+    let rate = entries.get("mutation.rate", "a number")?;
+    let mode = entries.get_with("mutation.mode", "per_gene or per_bit", |value| match value {
+        "per_gene" => Some(PointMutation::PerGene as fn(f64) -> PointMutation),
+        "per_bit" => Some(PointMutation::PerBit as fn(f64) -> PointMutation),
+        _ => None
+    })?;
+
+    Ok(match (mode, rate) {
+        (None, None) => SimulationConfig::default().point_mutation,
+        (mode, rate) => mode.unwrap_or(PointMutation::PerGene)(rate.ok_or(ConfigFileError::MissingKey("mutation.rate", "mutation.mode"))?)
+    })
+}
+
+fn parse_length_mutation(entries: &Entries) -> Result<Option<LengthMutation>, ConfigFileError> {
+    let is_enabled = ["mutation.insertion_rate", "mutation.deletion_rate", "mutation.min_genes", "mutation.max_genes"]
+        .iter()
+        .any(|key| entries.is_set(key));
+
+    if !is_enabled {
+        return Ok(None);
+    }
+
+    Ok(Some(LengthMutation {
+        insertion_rate: entries.get_or("mutation.insertion_rate", "a number", 0.0)?,
+        deletion_rate: entries.get_or("mutation.deletion_rate", "a number", 0.0)?,
+        min_genes: entries.require("mutation.min_genes", "an unsigned integer", "mutation.insertion_rate/deletion_rate")?,
+        max_genes: entries.require("mutation.max_genes", "an unsigned integer", "mutation.insertion_rate/deletion_rate")?,
+    }))
+}
+
+fn parse_reproduction(entries: &Entries) -> Result<Reproduction, ConfigFileError> {
+    let is_sexual = entries.get_with("reproduction.mode", "asexual or sexual", |value| match value {
+        "asexual" => Some(false),
+        "sexual" => Some(true),
+        _ => None
+    })?.unwrap_or(false);
+
+    if !is_sexual {
+        return Ok(Reproduction::Asexual);
+    }
+
+    let crossover = entries.get_with("reproduction.crossover", "single_point, two_point or uniform", |value| match value {
+        "single_point" => Some(Crossover::SinglePoint),
+        "two_point" => Some(Crossover::TwoPoint),
+        "uniform" => Some(Crossover::Uniform),
+        _ => None
+    })?.unwrap_or(Crossover::SinglePoint);
+
+    let pairing = entries.get_with("reproduction.pairing", "random, nearest_neighbour or genetic_similarity", |value| match value {
+        "random" => Some(Pairing::Random),
+        "nearest_neighbour" => Some(Pairing::NearestNeighbour),
+        "genetic_similarity" => Some(Pairing::GeneticSimilarity),
+        _ => None
+    })?.unwrap_or(Pairing::Random);
+
+    Ok(Reproduction::Sexual { crossover, pairing })
+}
+
+const CRITERION_KEY: &str = "selection.criterion";
+
+// `selection.criterion` is either a single criterion, or several combined with `&` (and),
+// `|` (or) and a `!` (not) prefix, e.g. `half_field & !corners | circle`
+// `&` binds tighter than `|`; there are no parentheses, and each criterion uses the same
+// `selection.*` parameters wherever it appears
+fn parse_selection(entries: &Entries) -> Result<Box<dyn SelectionCriterion>, ConfigFileError> {
+    let expression = entries.get_with(
+        CRITERION_KEY,
+        "everyone, half_field, circle, near_wall or corners, combined with &, | and !",
+        parse_criterion_expression
+    )?.unwrap_or(vec![vec![(false, "everyone".to_string())]]);
+
+    let mut any_of: Option<Box<dyn SelectionCriterion>> = None;
+    for terms in expression {
+        let mut all_of: Option<Box<dyn SelectionCriterion>> = None;
+        for (is_negated, name) in terms {
+            let criterion = match is_negated {
+                true => Box::new(parse_criterion(entries, &name)?.not()),
+                false => parse_criterion(entries, &name)?
+            };
+            all_of = Some(match all_of {
+                Some(all_of) => Box::new(all_of.and(criterion)),
+                None => criterion
+            });
+        }
+
+        let all_of = all_of.unwrap();
+        any_of = Some(match any_of {
+            Some(any_of) => Box::new(any_of.or(all_of)),
+            None => all_of
+        });
+    }
+
+    let selection = any_of.unwrap();
+    match entries.get_or("selection.invert", "true or false", false)? {
+        true => Ok(Box::new(selection.not())),
+        false => Ok(selection)
+    }
+}
+
+// `|` separated groups of `&` separated (negated, name) criteria
+fn parse_criterion_expression(value: &str) -> Option<Vec<Vec<(bool, String)>>> {
+    value.split('|')
+        .map(|group| {
+            group.split('&')
+                .map(|term| {
+                    let term = term.trim();
+                    let (is_negated, name) = match term.strip_prefix('!') {
+                        Some(name) => (true, name.trim()),
+                        None => (false, term)
+                    };
+
+                    match name {
+                        "everyone" | "half_field" | "circle" | "near_wall" | "corners" => Some((is_negated, name.to_string())),
+                        _ => None
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// A single built-in criterion, as validated by `parse_criterion_expression`
+fn parse_criterion(entries: &Entries, name: &str) -> Result<Box<dyn SelectionCriterion>, ConfigFileError> {
+    Ok(match name {
+        "half_field" => {
+            let side = entries.get_with("selection.side", "north, south, east or west", |value| match value {
+                "north" => Some(Side::North),
+                "south" => Some(Side::South),
+                "east" => Some(Side::East),
+                "west" => Some(Side::West),
+                _ => None
+            })?.ok_or(ConfigFileError::MissingKey("selection.side", CRITERION_KEY))?;
+
+            Box::new(HalfField(side))
+        }
+
+        "circle" => Box::new(Circle {
+            center: Vector2D::new(
+                entries.require("selection.center_x", "an unsigned integer", CRITERION_KEY)?,
+                entries.require("selection.center_y", "an unsigned integer", CRITERION_KEY)?
+            ),
+            radius: entries.require("selection.radius", "a number", CRITERION_KEY)?
+        }),
+
+        "near_wall" => Box::new(NearWall {
+            distance: entries.require("selection.distance", "an unsigned integer", CRITERION_KEY)?
+        }),
+
+        "corners" => Box::new(Corners {
+            size: entries.require("selection.size", "an unsigned integer", CRITERION_KEY)?
+        }),
+
+        _ => Box::new(Everyone)
+    })
+}
+
+// Lines and rectangles are lists of whitespace separated numbers, one shape per `;`
+// Order: all lines, then rectangles, then the random scatter
+fn parse_barriers(entries: &Entries) -> Result<Vec<Barrier>, ConfigFileError> {
+    let lines = entries.get_with("barriers.lines", "`x0 y0 x1 y1` lines, separated by `;`", |value| {
+        parse_shapes(value, |[x0, y0, x1, y1]| Barrier::Line {
+            start: Vector2D::new(x0, y0),
+            end: Vector2D::new(x1, y1)
+        })
+    })?.unwrap_or_default();
+
+    let rectangles = entries.get_with("barriers.rectangles", "`x y width height` rectangles, separated by `;`", |value| {
+        parse_shapes(value, |[x, y, width, height]| Barrier::Rectangle {
+            corner: Vector2D::new(x, y),
+            width,
+            height
+        })
+    })?.unwrap_or_default();
+
+    let random = entries.get("barriers.random", "an unsigned integer")?
+        .map(|total_cells| Barrier::RandomScatter { total_cells });
+
+    Ok(lines.into_iter().chain(rectangles).chain(random).collect())
+}
+
+fn parse_shapes(value: &str, to_barrier: impl Fn([usize; 4]) -> Barrier) -> Option<Vec<Barrier>> {
+    value.split(';')
+        .map(|shape| {
+            let numbers = shape.split_whitespace()
+                .map(|number| number.parse().ok())
+                .collect::<Option<Vec<usize>>>()?;
+            numbers.try_into().ok().map(&to_barrier)
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_config() {
+        let text = "
+            # Experiment #42
+            [simulation]
+            field_width = 64
+            field_height = 32
+            population = 100
+            seed = 1234
+            total_genes = 12
+            steps_per_generation = 150
+            movement_conflict = all_blocked
+            topology = toroidal
+            activation = genome
+            gene_format = wide
+
+            [mutation]
+            mode = per_bit
+            rate = 0.002
+            insertion_rate = 0.01   # inline comment
+            deletion_rate = 0.01
+            min_genes = 4
+            max_genes = 24
+
+            [reproduction]
+            mode = sexual
+            crossover = uniform
+            pairing = nearest_neighbour
+
+            [selection]
+            criterion = half_field
+            side = east
+
+            [barriers]
+            lines = 0 0 0 9; 10 5 20 5
+            rectangles = 30 10 4 2
+            random = 25
+
+            [renderer]
+            field_color = 0x112233
+            border_color = 445566
+
+            [output]
+            directory = ./runs/42
+            generations = 5
+
+            [checkpoint]
+            save = ./runs/42/last.ckpt
+        ";
+
+        let config = parse_config(text).unwrap();
+        let sim = &config.simulation;
+
+        assert_eq!((sim.field_width, sim.field_height), (64, 32));
+        assert_eq!(sim.initial_total_creature, 100);
+        assert_eq!(sim.seed[..8], 1234_u64.to_le_bytes());
+        assert_eq!(sim.total_genes, 12);
+        assert_eq!(sim.steps_per_generation, 150);
+        assert_eq!(sim.movement_conflict, MovementConflict::AllBlocked);
+        assert_eq!(sim.topology, Topology::Toroidal);
+        assert!(sim.brain.activation_from_genome);
+        assert_eq!(sim.brain.gene_format, GeneFormat::Wide);
+        assert_eq!(sim.point_mutation, PointMutation::PerBit(0.002));
+        assert_eq!(sim.length_mutation, Some(LengthMutation {
+            insertion_rate: 0.01,
+            deletion_rate: 0.01,
+            min_genes: 4,
+            max_genes: 24
+        }));
+        assert_eq!(sim.reproduction, Reproduction::Sexual {
+            crossover: Crossover::Uniform,
+            pairing: Pairing::NearestNeighbour
+        });
+
+        assert_eq!(config.field_color, Color::new(0x11, 0x22, 0x33));
+        assert_eq!(config.border_color, Color::new(0x44, 0x55, 0x66));
+        assert_eq!(config.barrier_color, None);
+        assert_eq!(sim.barriers, vec![
+            Barrier::Line { start: Vector2D::new(0, 0), end: Vector2D::new(0, 9) },
+            Barrier::Line { start: Vector2D::new(10, 5), end: Vector2D::new(20, 5) },
+            Barrier::Rectangle { corner: Vector2D::new(30, 10), width: 4, height: 2 },
+            Barrier::RandomScatter { total_cells: 25 },
+        ]);
+        assert_eq!(config.output_dir, PathBuf::from("./runs/42"));
+        assert_eq!(config.total_generations, 5);
+        assert_eq!(config.rendered_steps, DEFAULT_RENDERED_STEPS);
+        assert_eq!(config.population_file, None);
+        assert_eq!(config.load_checkpoint, None);
+        assert_eq!(config.save_checkpoint, Some(PathBuf::from("./runs/42/last.ckpt")));
+    }
+
+    #[test]
+    fn parse_empty_config() {
+        let config = parse_config("").unwrap();
+
+        assert_eq!(config.simulation.field_width, DEFAULT_FIELD_WIDTH);
+        assert_eq!(config.simulation.initial_total_creature, DEFAULT_POPULATION);
+        assert_eq!(config.simulation.length_mutation, None);
+        assert_eq!(config.simulation.reproduction, Reproduction::Asexual);
+        assert_eq!(config.output_dir, PathBuf::from(DEFAULT_OUTPUT_DIR));
+    }
+
+    #[test]
+    fn parse_combined_selection_criteria() {
+        let parse = |value: &str| parse_criterion_expression(value);
+        let term = |is_negated: bool, name: &str| (is_negated, name.to_string());
+
+        assert_eq!(parse("corners"), Some(vec![vec![term(false, "corners")]]));
+        assert_eq!(parse("half_field & !corners | ! circle"), Some(vec![
+            vec![term(false, "half_field"), term(true, "corners")],
+            vec![term(true, "circle")],
+        ]));
+        assert_eq!(parse("half_field &"), None);
+        assert_eq!(parse("half_field | square"), None);
+
+        let config = parse_config("[selection]\ncriterion = half_field & !near_wall\nside = west\ndistance = 1");
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn config_errors_name_the_key() {
+        let error_message = |text: &str| parse_config(text).err().unwrap().to_string();
+
+        assert_eq!(
+            error_message("[simulation]\npopulation = lots"),
+            "Line 2: invalid value `lots` for `simulation.population` (expected an unsigned integer)"
+        );
+        assert_eq!(error_message("[simulation]\nwidth = 10"), "Line 2: unknown key `simulation.width`");
+        assert_eq!(error_message("population = 10"), "Line 1: `population` is outside of any [section]");
+        assert_eq!(error_message("[simulation]\njust some words"), "Line 2: expected `[section]` or `key = value`");
+        assert_eq!(
+            error_message("[simulation]\nseed = 1\nseed = 2"),
+            "Line 3: `simulation.seed` is set more than once"
+        );
+        assert_eq!(
+            error_message("[selection]\ncriterion = circle\nradius = 3"),
+            "`selection.center_x` is required when `selection.criterion` is set"
+        );
+        assert_eq!(
+            error_message("[selection]\ncriterion = everyone & corners"),
+            "`selection.size` is required when `selection.criterion` is set"
+        );
+        assert!(error_message("[simulation]\nfield_width = 2\nfield_height = 2\npopulation = 5")
+            .starts_with("Invalid value for `simulation.population`"));
+        assert_eq!(
+            error_message("[barriers]\nlines = 0 0 5 5; 1 2 3"),
+            "Line 2: invalid value `0 0 5 5; 1 2 3` for `barriers.lines` (expected `x0 y0 x1 y1` lines, separated by `;`)"
+        );
+        assert!(error_message("[simulation]\ninternal_neurons = 100")
+            .starts_with("Invalid value for `simulation.internal_neurons`"));
+        assert_eq!(
+            error_message("[renderer]\nfield_color = +12345"),
+            "Line 2: invalid value `+12345` for `renderer.field_color` (expected a RRGGBB hex color)"
+        );
+        assert!(error_message("[mutation]\nrate = 2.0")
+            .starts_with("Invalid value for `mutation.rate/insertion_rate/deletion_rate`"));
+        assert!(error_message("[map]\nfile = ./no/such/map.txt")
+            .starts_with("Couldn't load `map.file`: Couldn't read map file"));
+    }
+}
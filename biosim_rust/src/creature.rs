@@ -1,17 +1,27 @@
 use std::error::Error;
-use rand::Rng;
-use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 
+use crate::checkpoint::{self, CheckpointError};
 use crate::genome::Genome;
 use crate::renderer::Color;
-use crate::neuron::{Brain, sensory_neuron, action_neuron};
-use sensory_neuron::SensoryNeuron;
-use action_neuron::ActionNeuron;
-use crate::simulation::{Signal, Simulation};
-use crate::vector2d::Vector2D;
+use crate::neuron::{Brain, BrainConfig, sensory_neuron, action_neuron};
+use sensory_neuron::{SensoryNeuron, TOTAL_SENSORY_NEURON_VARIANT};
+use action_neuron::{ActionNeuron, TOTAL_ACTION_NEURON_VARIANT};
+use crate::simulation::{SensorContext, Signal};
+use crate::vector2d::{Direction, Vector2D};
 
 pub type CreatureRng = ChaCha8Rng;
+pub type CreatureID = usize;
+
+// PopulationDensity looks at the (2 * radius + 1)^2 square around the creature
+const POPULATION_SENSOR_RADIUS: usize = 2;
+// How far LookForwardDistance can see
+const LOOK_AHEAD_DISTANCE: usize = 8;
+// Oscillator period (in steps) is picked from the Genome, within this range
+const MIN_OSCILLATOR_PERIOD: usize = 4;
+const MAX_OSCILLATOR_PERIOD: usize = 64;
 
 
 pub struct Creature {
@@ -19,88 +29,152 @@ pub struct Creature {
     genome: Genome,
 
     brain: Brain,
-    sensory_data: HashMap<SensoryNeuron, f64>,
-    action_data: HashMap<ActionNeuron, f64>,
+    // Indexed by neuron ID; only the neurons connected in the brain are used
+    sensory_data: [f64; TOTAL_SENSORY_NEURON_VARIANT],
+    action_data: [f64; TOTAL_ACTION_NEURON_VARIANT],
 
     color: Color,
-    rng: CreatureRng
+    rng: CreatureRng,
+
+    oscillator_period: usize,
+    last_move_blocked: bool,
+    last_move_direction: Direction
 }
 
 impl Creature {
-    pub fn new(position: Vector2D<usize>, genome: Genome, unique_stream_rng: CreatureRng) -> Result<Self, Box<dyn Error>> {
+    pub fn new(position: Vector2D<usize>, genome: Genome, unique_stream_rng: CreatureRng, brain_config: BrainConfig) -> Result<Self, Box<dyn Error>> {
         let color = genome.generate_color()?;
-        let brain = Brain::from_genome(&genome);
+        let brain = Brain::from_genome(&genome, brain_config);
 
-        let (sensory_data, action_data) = brain.neurons_empty_value_map();
+        let oscillator_period = oscillator_period_from_genome(&genome);
 
         Ok(Self {
             position,
             genome,
             brain,
-            sensory_data,
-            action_data,
+            sensory_data: [0.0; TOTAL_SENSORY_NEURON_VARIANT],
+            action_data: [0.0; TOTAL_ACTION_NEURON_VARIANT],
             color,
-            rng: unique_stream_rng
+            rng: unique_stream_rng,
+            oscillator_period,
+            last_move_blocked: false,
+            last_move_direction: Direction::North
         })
     }
 
-    // Ugly nesting, but either this or cloning the keys/using RefCells
-    pub fn gather_sensory_data(&mut self, sim: &Simulation) -> () {
-        for (neuron, value) in self.sensory_data.iter_mut() {
-            // Every single sensory data MUST be between -1.0 and 1.0
-            // Some sensory data might be between 0 and 1, and that's okay
-            *value = match neuron {
-                SensoryNeuron::Random => self.rng.gen_range(-1.0..=1.0),
+    // Only the sensors the brain reads from are updated
+    pub fn gather_sensory_data(&mut self, context: &SensorContext) -> () {
+        for i in 0..self.brain.sensors().len() {
+            let neuron = self.brain.sensors()[i];
+            self.sensory_data[neuron as usize] = self.sense(neuron, context);
+        }
+    }
 
-                // This part gonna be hell lul
-                SensoryNeuron::DistToBarrierNorth => self.position.y as f64 / sim.field_height() as f64,
-                SensoryNeuron::DistToBarrierSouth => 1.0 - (self.position.y as f64 / sim.field_height() as f64),
-                SensoryNeuron::DistToBarrierWest => self.position.x as f64 / sim.field_width() as f64,
-                SensoryNeuron::DistToBarrierEast => 1.0 - (self.position.x as f64 / sim.field_width() as f64),
-            }
+    fn sense(&mut self, neuron: SensoryNeuron, context: &SensorContext) -> f64 {
+        // Every single sensory data MUST be between -1.0 and 1.0
+        // Some sensory data might be between 0 and 1, and that's okay
+        match neuron {
+            SensoryNeuron::Random => self.rng.gen_range(-1.0..=1.0),
+
+            // Cells until the closest barrier or wall, relative to the field's size
+            // A torus has no walls, so without barriers there's nothing in sight (1.0)
+            SensoryNeuron::DistToBarrierNorth => barrier_distance(&self.position, Direction::North, context),
+            SensoryNeuron::DistToBarrierSouth => barrier_distance(&self.position, Direction::South, context),
+            SensoryNeuron::DistToBarrierWest => barrier_distance(&self.position, Direction::West, context),
+            SensoryNeuron::DistToBarrierEast => barrier_distance(&self.position, Direction::East, context),
+
+            SensoryNeuron::Age => context.current_step() as f64 / context.steps_per_generation() as f64,
+            SensoryNeuron::Oscillator => {
+                let phase = (context.current_step() % self.oscillator_period) as f64 / self.oscillator_period as f64;
+                (phase * std::f64::consts::TAU).sin()
+            },
+            SensoryNeuron::PopulationDensity => population_density(&self.position, context),
+            SensoryNeuron::BlockedForward => match self.last_move_blocked {
+                true => 1.0,
+                false => 0.0
+            },
+
+            SensoryNeuron::LookForwardDistance => {
+                let distance = context.free_cells_along(&self.position, self.last_move_direction, LOOK_AHEAD_DISTANCE);
+                distance as f64 / LOOK_AHEAD_DISTANCE as f64
+            },
+            SensoryNeuron::LastMoveDirX => self.last_move_direction.offset().x as f64,
+            SensoryNeuron::LastMoveDirY => self.last_move_direction.offset().y as f64,
         }
     }
 
-    pub fn execute_actions(&mut self, sim: &Simulation) -> Vec<Signal> {
+    pub fn execute_actions(&mut self, context: &SensorContext) -> Vec<Signal> {
         let mut signals = vec![];
-        let mut raw_movement_value = Vector2D::new(0.0, 0.0);
-
-        let mut normalized_value: f64;
-        for (neuron, &value) in self.action_data.iter() {
-            normalized_value = (value.tanh() + 1.0) / 2.0;
-            match neuron {
-                ActionNeuron::MoveNorth => raw_movement_value.y -= normalized_value,
-                ActionNeuron::MoveSouth => raw_movement_value.y += normalized_value,
-                ActionNeuron::MoveEast => raw_movement_value.x += normalized_value,
-                ActionNeuron::MoveWest => raw_movement_value.x -= normalized_value,
-            }
-        }
+        let raw_movement_value = self.sum_movement_actions();
 
-        if let Some(pos_change) = self.process_raw_movement_value(raw_movement_value, sim) {
+        if let Some(pos_change) = self.process_raw_movement_value(raw_movement_value, context) {
             signals.push(pos_change);
         }
 
         signals
     }
 
-    fn process_raw_movement_value(&mut self, value: Vector2D<f64>, sim: &Simulation) -> Option<Signal> {
-        // We see if the creature is 'determined' to move (using Rng), and move them 1 pixel in the
-        // desired direction
-        let mut movement = Vector2D::new(0,0);
-        if value.x != 0.0 {
-            movement.x = value.x.signum() as usize;
+    // Every move action pushes the creature toward its direction by tanh of its output, so a
+    // silent action doesn't push at all, and a negative one pushes the opposite way
+    fn sum_movement_actions(&mut self) -> Vector2D<f64> {
+        let mut raw_movement_value = Vector2D::new(0.0, 0.0);
+
+        // Summed in ActionNeuron ID order (the brain's actions are sorted), as float addition
+        // isn't associative (opposite moves might not cancel out exactly)
+        let mut normalized_value: f64;
+        let mut direction: Direction;
+        for i in 0..self.brain.actions().len() {
+            let neuron = self.brain.actions()[i];
+            let value = self.action_data[neuron as usize];
+            normalized_value = value.tanh();
+            // Relative moves are turned from the last heading, in 45 degree steps
+            direction = match neuron {
+                ActionNeuron::MoveNorth => Direction::North,
+                ActionNeuron::MoveSouth => Direction::South,
+                ActionNeuron::MoveEast => Direction::East,
+                ActionNeuron::MoveWest => Direction::West,
+                ActionNeuron::MoveForward => self.last_move_direction,
+                ActionNeuron::MoveReverse => self.last_move_direction.rotate(4),
+                ActionNeuron::MoveLeft => self.last_move_direction.rotate(-2),
+                ActionNeuron::MoveRight => self.last_move_direction.rotate(2),
+                ActionNeuron::MoveRandom => Direction::random(&mut self.rng),
+            };
+
+            let offset = direction.offset();
+            raw_movement_value.x += offset.x as f64 * normalized_value;
+            raw_movement_value.y += offset.y as f64 * normalized_value;
+        }
+
+        raw_movement_value
+    }
+
+    fn process_raw_movement_value(&mut self, value: Vector2D<f64>, context: &SensorContext) -> Option<Signal> {
+        // We see if the creature is 'determined' to move (using Rng), and request to move them
+        // 1 pixel in the desired direction
+        // Each axis moves toward the sign of its summed push, with a probability of |push| (capped
+        // at 1.0), so weak outputs only move once in a while
+        // The move itself is done by Simulation, as other creatures might want the same spot
+        let mut movement: Vector2D<isize> = Vector2D::new(0, 0);
+        if value.x != 0.0 && self.rng.gen_bool(value.x.abs().min(1.0)) {
+            movement.x = value.x.signum() as isize;
         }
 
-        if value.y != 0.0 {
-            movement.y = value.y.signum() as usize;
+        if value.y != 0.0 && self.rng.gen_bool(value.y.abs().min(1.0)) {
+            movement.y = value.y.signum() as isize;
         }
 
-        if movement != Vector2D::new(0, 0) {
-            let new_position = self.position + movement;
-            if let Some(false) = sim.is_position_occupied(&new_position) {
-                self.position = new_position;
+        if let Some(direction) = Direction::from_offset(movement.x, movement.y) {
+            // The creature turns toward where it wants to go, even if it ends up blocked
+            self.last_move_direction = direction;
+            // Simulation clears this once the move actually happens
+            self.last_move_blocked = true;
+
+            // Walking into a wall is just another way of being blocked
+            let (new_position, direction) = context.step_position(&self.position, direction)?;
+            self.last_move_direction = direction;
+            if let Some(false) = context.is_position_occupied(&new_position) {
                 return Some(
-                    Signal::PositionChanged { old: self.position - movement, new: new_position }
+                    Signal::MoveRequested { new: new_position }
                 );
             }
         }
@@ -109,16 +183,31 @@ impl Creature {
     }
 
     pub fn think(&mut self) {
-        self.brain.process_connections(
-            &self.sensory_data,
-            &mut self.action_data
-        );
+        self.brain.think(&self.sensory_data, &mut self.action_data);
+    }
+
+    // Clear the brain's memory and last outputs, so nothing leaks from a previous generation
+    pub fn reset_brain(&mut self) {
+        self.brain.reset();
+        self.action_data.fill(0.0);
     }
 
     pub fn position(&self) -> &Vector2D<usize> {
         &self.position
     }
 
+    pub fn set_position(&mut self, position: Vector2D<usize>) {
+        self.position = position;
+    }
+
+    pub fn set_last_move_blocked(&mut self, blocked: bool) {
+        self.last_move_blocked = blocked;
+    }
+
+    pub fn set_last_move_direction(&mut self, direction: Direction) {
+        self.last_move_direction = direction;
+    }
+
     pub fn color(&self) -> Color {
         self.color
     }
@@ -126,6 +215,124 @@ impl Creature {
     pub fn brain(&self) -> &Brain {
         &self.brain
     }
+
+    pub fn genome(&self) -> &Genome {
+        &self.genome
+    }
+
+    // Layout: position, genes, CreatureRng (seed, stream, word pos.), internal neuron states,
+    // the (ActionNeuron ID, value) pairs sorted by ID, whether the last move was blocked,
+    // then the last move Direction
+    // Brain, sensory values and color are all rebuilt from the Genome
+    pub fn write_checkpoint<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        checkpoint::write_usize(writer, self.position.x)?;
+        checkpoint::write_usize(writer, self.position.y)?;
+
+        checkpoint::write_usize(writer, self.genome.genes().len())?;
+        for &gene in self.genome.genes() {
+            checkpoint::write_u16(writer, gene)?;
+        }
+
+        writer.write_all(&self.rng.get_seed())?;
+        checkpoint::write_u64(writer, self.rng.get_stream())?;
+        checkpoint::write_u128(writer, self.rng.get_word_pos())?;
+
+        let internal_neurons = self.brain.internal_neurons();
+        checkpoint::write_usize(writer, internal_neurons.len())?;
+        for neuron in internal_neurons {
+            checkpoint::write_f64(writer, neuron.state())?;
+        }
+
+        let actions = self.brain.actions();
+        checkpoint::write_usize(writer, actions.len())?;
+        for &neuron in actions {
+            checkpoint::write_usize(writer, neuron as usize)?;
+            checkpoint::write_f64(writer, self.action_data[neuron as usize])?;
+        }
+
+        writer.write_all(&[self.last_move_blocked as u8, self.last_move_direction as u8])
+    }
+
+    pub fn read_checkpoint<R: Read>(reader: &mut R, brain_config: BrainConfig) -> Result<Self, CheckpointError> {
+        let position = Vector2D::new(checkpoint::read_usize(reader)?, checkpoint::read_usize(reader)?);
+
+        let total_genes = checkpoint::read_usize(reader)?;
+        let genes = (0..total_genes)
+            .map(|_| checkpoint::read_u16(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut rng = CreatureRng::from_seed(checkpoint::read_bytes(reader)?);
+        rng.set_stream(checkpoint::read_u64(reader)?);
+        rng.set_word_pos(checkpoint::read_u128(reader)?);
+
+        let mut creature = Creature::new(position, Genome::from_genes(genes), rng, brain_config)
+            .map_err(|err| CheckpointError::InvalidCreature(err.to_string()))?;
+
+        let total_internal_neurons = checkpoint::read_usize(reader)?;
+        if total_internal_neurons != brain_config.total_internal_neurons {
+            return Err(CheckpointError::InternalNeuronMismatch(total_internal_neurons, brain_config.total_internal_neurons));
+        }
+        for neuron in creature.brain.internal_neurons_mut() {
+            neuron.set_state(checkpoint::read_f64(reader)?);
+        }
+
+        let total_action_data = checkpoint::read_usize(reader)?;
+        for _ in 0..total_action_data {
+            let id = checkpoint::read_usize(reader)?;
+            let neuron = ActionNeuron::from_id(id).ok_or(CheckpointError::InvalidNeuronID(id))?;
+            creature.action_data[neuron as usize] = checkpoint::read_f64(reader)?;
+        }
+
+        let [last_move_blocked, last_move_direction] = checkpoint::read_bytes(reader)?;
+        creature.last_move_blocked = last_move_blocked != 0;
+        creature.last_move_direction = Direction::from_id(last_move_direction as usize)
+            .ok_or_else(|| CheckpointError::InvalidCreature(format!("unknown Direction ID {}", last_move_direction)))?;
+
+        Ok(creature)
+    }
+}
+
+// Every Genome always gets the same period, so offspring inherit (roughly) their parent's rhythm
+fn oscillator_period_from_genome(genome: &Genome) -> usize {
+    let hash = genome.genes().iter().fold(0_usize, |acc, &gene| acc ^ gene as usize);
+    MIN_OSCILLATOR_PERIOD + hash % (MAX_OSCILLATOR_PERIOD - MIN_OSCILLATOR_PERIOD + 1)
+}
+
+fn barrier_distance(pos: &Vector2D<usize>, direction: Direction, context: &SensorContext) -> f64 {
+    let field_size = match direction.offset().x {
+        0 => context.field_height(),
+        _ => context.field_width()
+    };
+
+    context.cells_until_barrier(pos, direction, field_size) as f64 / field_size as f64
+}
+
+// Fraction of the neighbouring cells (within the field) taken by other creatures
+// Barriers count as cells, but not as population
+// On a torus, the neighbourhood wraps around the edges
+fn population_density(pos: &Vector2D<usize>, context: &SensorContext) -> f64 {
+    let radius = POPULATION_SENSOR_RADIUS as isize;
+
+    let mut total_cells = 0;
+    let mut total_occupied = 0;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let cell = match context.translate_position(pos, Vector2D::new(dx, dy)) {
+                Some(cell) if cell != *pos => cell,
+                _ => continue
+            };
+
+            total_cells += 1;
+            if context.creature_at(&cell).is_some() {
+                total_occupied += 1;
+            }
+        }
+    }
+
+    match total_cells {
+        0 => 0.0,
+        _ => total_occupied as f64 / total_cells as f64
+    }
 }
 
 
@@ -134,46 +341,182 @@ mod tests {
     use rand::SeedableRng;
 
     use super::*;
-    use sensory_neuron::TOTAL_SENSORY_NEURON_VARIANT;
+    use crate::barrier::Barrier;
+    use crate::genome::Gene;
+    use crate::population_file::parse_population;
+    use crate::simulation::{Simulation, SimulationConfigBuilder};
+    use crate::topology::Topology;
 
     fn gen_simulation() -> Simulation {
-        Simulation::new(100, 100, 5, [0; 32], 10)
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(100, 100)
+            .with_population(5)
+            .with_total_genes(10)
+            .build()
+            .unwrap();
+
+        Simulation::new(config)
     }
 
     fn gen_creature() -> Creature {
         let genome = Genome::from_byte_slice(&[0; 20]);
-        let brain = Brain::from_genome(&genome);
+        let brain = Brain::from_genome(&genome, BrainConfig::default());
         Creature {
             position: Vector2D::new(4, 10),
             genome,
 
             brain,
-            sensory_data: HashMap::new(),
-            action_data: HashMap::new(),
+            sensory_data: [0.0; TOTAL_SENSORY_NEURON_VARIANT],
+            action_data: [0.0; TOTAL_ACTION_NEURON_VARIANT],
 
             color: Color::new(0, 0, 0),
-            rng: CreatureRng::from_seed([0; 32])
+            rng: CreatureRng::from_seed([0; 32]),
+
+            oscillator_period: 10,
+            last_move_blocked: false,
+            last_move_direction: Direction::North
         }
     }
 
+    fn context(sim: &Simulation) -> SensorContext<'_> {
+        SensorContext::new(sim.config(), sim.grid(), sim.current_step())
+    }
+
+    // Every sensor, whether the brain reads it or not
+    fn sense_everything(creature: &mut Creature, sim: &Simulation) {
+        for id in 0..TOTAL_SENSORY_NEURON_VARIANT {
+            creature.sensory_data[id] = creature.sense(SensoryNeuron::from_id(id).unwrap(), &context(sim));
+        }
+    }
+
+    // SensoryToAction connection toward `neuron`, with a weight of 0
+    fn gene_to_action(neuron: ActionNeuron) -> Gene {
+        ((neuron as Gene) << 4) | 0x8
+    }
+
     #[test]
     fn test_gathering_sensory_data() -> () {
         let mut creature = gen_creature();
         let sim = gen_simulation();
 
-        for id in 0..TOTAL_SENSORY_NEURON_VARIANT {
-            creature.sensory_data.insert(SensoryNeuron::from_id(id).unwrap(), 0.0);
-        }
 
-        creature.gather_sensory_data(&sim);
+        sense_everything(&mut creature, &sim);
 
         let sensory_data = creature.sensory_data;
-        assert_eq!(sensory_data[&SensoryNeuron::Random], 0.6738395137652948);
+        assert_eq!(sensory_data[SensoryNeuron::Random as usize], 0.6738395137652948);
+
+        assert_eq!(sensory_data[SensoryNeuron::DistToBarrierNorth as usize], 0.1);
+        assert_eq!(sensory_data[SensoryNeuron::DistToBarrierSouth as usize], 0.89);
+        assert_eq!(sensory_data[SensoryNeuron::DistToBarrierWest as usize], 0.04);
+        assert_eq!(sensory_data[SensoryNeuron::DistToBarrierEast as usize], 0.95);
+
+        assert_eq!(sensory_data[SensoryNeuron::Age as usize], 0.0);
+        assert_eq!(sensory_data[SensoryNeuron::Oscillator as usize], 0.0);
+        assert_eq!(sensory_data[SensoryNeuron::PopulationDensity as usize], 0.0);
+        assert_eq!(sensory_data[SensoryNeuron::BlockedForward as usize], 0.0);
+
+        // Facing North from (4, 10), nothing in sight
+        assert_eq!(sensory_data[SensoryNeuron::LookForwardDistance as usize], 1.0);
+        assert_eq!(sensory_data[SensoryNeuron::LastMoveDirX as usize], 0.0);
+        assert_eq!(sensory_data[SensoryNeuron::LastMoveDirY as usize], -1.0);
+    }
+
+    #[test]
+    fn sense_neighbours_and_time() {
+        let mut creature = gen_creature();
+        creature.oscillator_period = 12;
+
+        // Creature sits at (4, 10): 2 neighbours within the sensor radius, 1 outside of it
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(100, 100)
+            .with_population(3)
+            .with_total_genes(1)
+            .with_steps_per_generation(20)
+            .build()
+            .unwrap();
+        let mut sim = Simulation::new(config);
+        sim.init_from_population(parse_population("0000 3 9\n0000 5 11\n0000 7 10").unwrap()).unwrap();
+
+        // Heading into an occupied cell
+        assert!(creature.process_raw_movement_value(Vector2D::new(20.0, 20.0), &context(&sim)).is_none());
+        sense_everything(&mut creature, &sim);
+        assert_eq!(creature.sensory_data[SensoryNeuron::PopulationDensity as usize], 2.0 / 24.0);
+        assert_eq!(creature.sensory_data[SensoryNeuron::BlockedForward as usize], 1.0);
+        // Turned toward the creature at (5, 11), right next to it
+        assert_eq!(creature.last_move_direction, Direction::SouthEast);
+        assert_eq!(creature.sensory_data[SensoryNeuron::LookForwardDistance as usize], 0.0);
+        assert_eq!(creature.sensory_data[SensoryNeuron::LastMoveDirX as usize], 1.0);
+        assert_eq!(creature.sensory_data[SensoryNeuron::LastMoveDirY as usize], 1.0);
+
+        for _ in 0..3 {
+            sim.step();
+        }
+        sense_everything(&mut creature, &sim);
+        assert_eq!(creature.sensory_data[SensoryNeuron::Age as usize], 3.0 / 20.0);
+        assert_eq!(creature.sensory_data[SensoryNeuron::Oscillator as usize], 1.0);
+
+        for value in creature.sensory_data.iter() {
+            assert!((-1.0..=1.0).contains(value));
+        }
+    }
+
+    #[test]
+    fn barriers_are_not_population() {
+        let mut creature = gen_creature();
+
+        // Creature sits at (4, 10), with a wall 1 cell to the east and 1 neighbour
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(100, 100)
+            .with_population(1)
+            .with_total_genes(1)
+            .with_barrier(Barrier::Line { start: Vector2D::new(5, 8), end: Vector2D::new(5, 12) })
+            .build()
+            .unwrap();
+        let mut sim = Simulation::new(config);
+        sim.init_from_population(parse_population("0000 3 9").unwrap()).unwrap();
+
+        sense_everything(&mut creature, &sim);
+        assert_eq!(creature.sensory_data[SensoryNeuron::PopulationDensity as usize], 1.0 / 24.0);
+    }
+
+    #[test]
+    fn oscillator_period_in_range() {
+        let mut rng = CreatureRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let genes = (0..8).map(|_| rng.gen()).collect();
+            let period = oscillator_period_from_genome(&Genome::from_genes(genes));
+            assert!((MIN_OSCILLATOR_PERIOD..=MAX_OSCILLATOR_PERIOD).contains(&period));
+        }
+    }
+
+    #[test]
+    fn combine_relative_and_absolute_moves() {
+        // Direction of the summed movement
+        let move_direction = |heading: Direction, actions: &[ActionNeuron]| {
+            let genes = actions.iter().map(|&neuron| gene_to_action(neuron)).collect();
+            let mut creature = gen_creature();
+            creature.brain = Brain::from_genome(&Genome::from_genes(genes), BrainConfig::default());
+            creature.last_move_direction = heading;
+            for &neuron in actions {
+                creature.action_data[neuron as usize] = 10.0;
+            }
 
-        assert_eq!(sensory_data[&SensoryNeuron::DistToBarrierNorth], 0.1);
-        assert_eq!(sensory_data[&SensoryNeuron::DistToBarrierSouth], 1.0 - 0.1);
-        assert_eq!(sensory_data[&SensoryNeuron::DistToBarrierWest], 0.04);
-        assert_eq!(sensory_data[&SensoryNeuron::DistToBarrierEast], 1.0 - 0.04);
+            let value = creature.sum_movement_actions();
+            (value.x.round() as isize, value.y.round() as isize)
+        };
+
+        assert_eq!(move_direction(Direction::South, &[ActionNeuron::MoveForward]), (0, 1));
+        assert_eq!(move_direction(Direction::West, &[ActionNeuron::MoveReverse]), (1, 0));
+        assert_eq!(move_direction(Direction::North, &[ActionNeuron::MoveRight]), (1, 0));
+        assert_eq!(move_direction(Direction::NorthEast, &[ActionNeuron::MoveLeft]), (-1, -1));
+        assert_eq!(move_direction(Direction::East, &[ActionNeuron::MoveLeft, ActionNeuron::MoveSouth]), (0, 0));
+        assert_eq!(move_direction(Direction::South, &[ActionNeuron::MoveForward, ActionNeuron::MoveEast]), (1, 1));
+
+        // MoveRandom only relies on the creature's own Rng
+        assert_eq!(
+            move_direction(Direction::North, &[ActionNeuron::MoveRandom]),
+            move_direction(Direction::North, &[ActionNeuron::MoveRandom])
+        );
     }
 
     #[test]
@@ -182,16 +525,125 @@ mod tests {
         creature.position = Vector2D::new(10, 10);
 
         let sim = gen_simulation();
-        let mut signal = creature.process_raw_movement_value(Vector2D::new(0.2, 0.8), &sim);
+        // A push of 1.0 or more is certain to move the creature
+        let mut signal = creature.process_raw_movement_value(Vector2D::new(20.0, 20.0), &context(&sim));
 
-        assert!(signal.is_some());
-        assert_eq!(creature.position.x, 11);
-        assert_eq!(creature.position.y, 11);
+        assert!(matches!(
+            signal,
+            Some(Signal::MoveRequested { new }) if new == Vector2D::new(11, 11)
+        ));
+        // Creature only moves once Simulation accepts the request
+        assert_eq!(creature.position.x, 10);
+        assert_eq!(creature.position.y, 10);
 
-        signal = creature.process_raw_movement_value(Vector2D::new(0.0, 0.0), &sim);
+        signal = creature.process_raw_movement_value(Vector2D::new(0.0, 0.0), &context(&sim));
 
         assert!(signal.is_none());
-        assert_eq!(creature.position.x, 11);
-        assert_eq!(creature.position.y, 11);
+    }
+
+    #[test]
+    fn move_in_all_directions() {
+        let sim = gen_simulation();
+        let mut creature = gen_creature();
+        creature.position = Vector2D::new(10, 10);
+
+        for (value, expected) in [
+            ((0.0, -20.0), (10, 9)),
+            ((20.0, -20.0), (11, 9)),
+            ((20.0, 0.0), (11, 10)),
+            ((20.0, 20.0), (11, 11)),
+            ((0.0, 20.0), (10, 11)),
+            ((-20.0, 20.0), (9, 11)),
+            ((-20.0, 0.0), (9, 10)),
+            ((-20.0, -20.0), (9, 9)),
+        ] {
+            let signal = creature.process_raw_movement_value(Vector2D::new(value.0, value.1), &context(&sim));
+            assert!(matches!(
+                signal,
+                Some(Signal::MoveRequested { new }) if new == Vector2D::new(expected.0, expected.1)
+            ), "{:?} should move to {:?}", value, expected);
+            assert_eq!(creature.last_move_direction, Direction::from_offset(value.0 as isize, value.1 as isize).unwrap());
+        }
+    }
+
+    #[test]
+    fn blocked_by_field_borders() {
+        // Field is 100x100
+        let sim = gen_simulation();
+        let mut creature = gen_creature();
+
+        for (position, value) in [
+            ((0, 50), (-20.0, 0.0)),
+            ((50, 0), (0.0, -20.0)),
+            ((99, 50), (20.0, 0.0)),
+            ((50, 99), (0.0, 20.0)),
+            ((0, 0), (-20.0, -20.0)),
+            ((99, 0), (20.0, -20.0)),
+            ((0, 99), (-20.0, 20.0)),
+            ((99, 99), (20.0, 20.0)),
+            // Sliding along a wall still counts as running into it
+            ((0, 50), (-20.0, 20.0)),
+        ] {
+            creature.position = Vector2D::new(position.0, position.1);
+            creature.last_move_blocked = false;
+
+            let signal = creature.process_raw_movement_value(Vector2D::new(value.0, value.1), &context(&sim));
+            assert!(signal.is_none(), "{:?} shouldn't move off the field from {:?}", value, position);
+            assert!(creature.last_move_blocked);
+        }
+
+        // Moving away from the corner is fine
+        creature.position = Vector2D::new(0, 0);
+        assert!(creature.process_raw_movement_value(Vector2D::new(20.0, 20.0), &context(&sim)).is_some());
+    }
+
+    #[test]
+    fn move_and_sense_on_a_torus() {
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(100, 100)
+            .with_total_genes(10)
+            .with_topology(Topology::Toroidal)
+            .build()
+            .unwrap();
+        let sim = Simulation::new(config);
+
+        let mut creature = gen_creature();
+        creature.position = Vector2D::new(0, 0);
+
+        let signal = creature.process_raw_movement_value(Vector2D::new(-20.0, -20.0), &context(&sim));
+        assert!(matches!(signal, Some(Signal::MoveRequested { new }) if new == Vector2D::new(99, 99)));
+
+        sense_everything(&mut creature, &sim);
+        assert_eq!(creature.sensory_data[SensoryNeuron::DistToBarrierNorth as usize], 1.0);
+        assert_eq!(creature.sensory_data[SensoryNeuron::DistToBarrierWest as usize], 1.0);
+    }
+
+    #[test]
+    fn weak_movement_is_occasional() {
+        // An all-zero genome only wires up MoveNorth
+        let mut creature = gen_creature();
+        assert_eq!(creature.brain.actions(), [ActionNeuron::MoveNorth]);
+        let sim = gen_simulation();
+
+        let mut try_moving = |output: f64| {
+            creature.action_data[ActionNeuron::MoveNorth as usize] = output;
+            let movement = creature.sum_movement_actions();
+            creature.process_raw_movement_value(movement, &context(&sim))
+        };
+
+        // A silent action never moves the creature
+        assert!((0..1000).all(|_| try_moving(0.0).is_none()));
+
+        // A negative output pushes the other way
+        assert!(matches!(try_moving(-20.0), Some(Signal::MoveRequested { new }) if new == Vector2D::new(4, 11)));
+
+        let total_tries = 10_000;
+        let total_moves = (0..total_tries)
+            .filter(|_| try_moving(0.5).is_some())
+            .count();
+
+        // tanh(0.5) ~= 0.462
+        let move_ratio = total_moves as f64 / total_tries as f64;
+        assert!((move_ratio - 0.5_f64.tanh()).abs() < 0.02, "moved {} times out of {}", total_moves, total_tries);
     }
 }
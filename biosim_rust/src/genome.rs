@@ -1,11 +1,47 @@
-use rand::SeedableRng;
+use rand::Rng;
 use thiserror::Error;
 
 use crate::renderer::Color;
 
 pub type Gene = u16;
+const GENE_BITS: u32 = Gene::BITS;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Genome(Vec<Gene>);
 
+// How point mutations are rolled during replication
+// Rates are probabilities, and will be clamped to 0.0..=1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointMutation {
+    // Every bit of every Gene has its own chance of being flipped
+    PerBit(f64),
+    // Every Gene has a chance of getting one of its bits (picked randomly) flipped
+    PerGene(f64),
+}
+
+// Insertion/deletion of whole Genes during replication, so Genome length can evolve
+// Each replication rolls (at most) one insertion and one deletion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthMutation {
+    pub insertion_rate: f64,
+    pub deletion_rate: f64,
+    // Genome length bounds; insertion/deletion that would break them is skipped
+    // A Genome always keeps at least 1 Gene
+    pub min_genes: usize,
+    pub max_genes: usize,
+}
+
+// How 2 parents' genes are combined during sexual reproduction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossover {
+    // Head of the first parent, tail of the second
+    SinglePoint,
+    // First parent, with a middle segment taken from the second parent
+    TwoPoint,
+    // Every Gene is picked from either parent with equal chance
+    Uniform,
+}
+
 // Reminder: Genome uses little-endian ordering
 impl Genome {
     // Combine 2 bytes, and collect
@@ -23,6 +59,36 @@ impl Genome {
         Genome(result)
     }
 
+    // Hex text form: 4 hex digits per Gene, in the same (little-endian) byte order as
+    // `from_byte_slice`, so a Gene 0xABCD is written as "cdab"
+    pub fn from_hex(hex: &str) -> Result<Self, GenomeError> {
+        let hex = hex.trim();
+        if !hex.len().is_multiple_of(4) {
+            return Err(GenomeError::InvalidHexLength(hex.len()));
+        }
+
+        if let Some(i) = hex.find(|c: char| !c.is_ascii_hexdigit()) {
+            return Err(GenomeError::InvalidHexDigit(i));
+        }
+
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap())
+            .collect();
+
+        Ok(Self::from_byte_slice(&bytes))
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter()
+            .map(|gene| format!("{:02x}{:02x}", gene & 0xFF, gene >> 8))
+            .collect()
+    }
+
+    pub fn from_genes(genes: Vec<Gene>) -> Self {
+        Genome(genes)
+    }
+
     pub fn genes(&self) -> &Vec<Gene> {
         &self.0
     }
@@ -30,7 +96,8 @@ impl Genome {
     // XOR the hell out of it until a u32 is left
     // What's the endianness of each Gene? Just gonna make it little-endian
     pub fn generate_color(&self) -> Result<Color, GenomeError> {
-        let mut val: u32 = (self.0[0] as u32) | ((self.0[1] as u32) << 16);
+        let first_gene = *self.0.first().ok_or(GenomeError::EmptyGenome)?;
+        let mut val: u32 = (first_gene as u32) | ((self.0.get(1).map_or(0, |x| *x) as u32) << 16);
 
         for i in 2..self.0.len() {
             if i % 2 == 1 { continue }
@@ -44,19 +111,126 @@ impl Genome {
         Ok(Color::from_xrgb_u32(val))
     }
 
-    pub fn replicate<R: SeedableRng>(&mut self, rng: R) -> Self {
-        todo!()
+    // Copy the Genome, then mutate the copy
+    // Pass the creature's own CreatureRng here, so the offspring is reproducible from RngSeed
+    pub fn replicate<R: Rng>(&self, rng: &mut R, mutation: PointMutation) -> Self {
+        let mut offspring = self.clone();
+        offspring.randomly_mutate(rng, mutation);
+
+        offspring
     }
 
-    fn randomly_mutate<R: SeedableRng>(&mut self, rng: R) {
-        todo!()
+    // Randomly insert and/or delete a Gene, keeping the length within bounds
+    // A Genome already out of bounds (e.g. loaded from a population file) is first trimmed
+    // down, or padded with random Genes, to the nearest bound
+    pub fn mutate_length<R: Rng>(&mut self, rng: &mut R, mutation: LengthMutation) {
+        let min_genes = mutation.min_genes.max(1);
+
+        self.0.truncate(mutation.max_genes.max(min_genes));
+        while self.0.len() < min_genes {
+            self.0.push(rng.gen());
+        }
+
+        if rng.gen_bool(mutation.insertion_rate.clamp(0.0, 1.0)) && self.0.len() < mutation.max_genes {
+            let index = rng.gen_range(0..=self.0.len());
+            self.0.insert(index, rng.gen());
+        }
+
+        if rng.gen_bool(mutation.deletion_rate.clamp(0.0, 1.0)) && self.0.len() > min_genes {
+            let index = rng.gen_range(0..self.0.len());
+            self.0.remove(index);
+        }
+    }
+
+    // Produce a child Genome from 2 parents
+    // Crossover points are only picked within the shorter Genome, so parents with different
+    // lengths are fine: whatever is left past that point is inherited from one of the parents
+    pub fn crossover<R: Rng>(&self, other: &Genome, rng: &mut R, crossover: Crossover) -> Self {
+        let shorter_len = self.0.len().min(other.0.len());
+
+        let result = match crossover {
+            Crossover::SinglePoint => {
+                let point = rng.gen_range(0..=shorter_len);
+                [&self.0[..point], &other.0[point..]].concat()
+            }
+
+            Crossover::TwoPoint => {
+                let mut start = rng.gen_range(0..=shorter_len);
+                let mut end = rng.gen_range(0..=shorter_len);
+                if start > end {
+                    std::mem::swap(&mut start, &mut end);
+                }
+
+                [&self.0[..start], &other.0[start..end], &self.0[end..]].concat()
+            }
+
+            Crossover::Uniform => {
+                let mut result: Vec<Gene> = self.0.iter()
+                    .zip(other.0.iter())
+                    .map(|(&a, &b)| if rng.gen_bool(0.5) { a } else { b })
+                    .collect();
+
+                let longer = if self.0.len() > other.0.len() { &self.0 } else { &other.0 };
+                if rng.gen_bool(0.5) {
+                    result.extend_from_slice(&longer[shorter_len..]);
+                }
+
+                result
+            }
+        };
+
+        Genome(result)
+    }
+
+    // Fraction of identical bits over the overlapping part of 2 Genomes (0.0..=1.0)
+    // Genomes without any overlap are considered completely different
+    pub fn similarity(&self, other: &Genome) -> f64 {
+        let shorter_len = self.0.len().min(other.0.len());
+        if shorter_len == 0 {
+            return 0.0;
+        }
+
+        let differing_bits: u32 = self.0.iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+
+        1.0 - differing_bits as f64 / (shorter_len as u32 * GENE_BITS) as f64
+    }
+
+    fn randomly_mutate<R: Rng>(&mut self, rng: &mut R, mutation: PointMutation) {
+        match mutation {
+            PointMutation::PerBit(rate) => {
+                let rate = rate.clamp(0.0, 1.0);
+                for gene in self.0.iter_mut() {
+                    for bit in 0..GENE_BITS {
+                        if rng.gen_bool(rate) {
+                            *gene ^= 1 << bit;
+                        }
+                    }
+                }
+            }
+
+            PointMutation::PerGene(rate) => {
+                let rate = rate.clamp(0.0, 1.0);
+                for gene in self.0.iter_mut() {
+                    if rng.gen_bool(rate) {
+                        *gene ^= 1 << rng.gen_range(0..GENE_BITS);
+                    }
+                }
+            }
+        }
     }
 }
 
 #[derive(Debug, Error)]
 pub enum GenomeError {
     #[error("Genome is empty")]
-    EmptyGenome
+    EmptyGenome,
+    #[error("Hex Genome should have 4 digits per Gene, got {0} digits")]
+    InvalidHexLength(usize),
+    #[error("Invalid hex digit at position {0}")]
+    InvalidHexDigit(usize)
 }
 
 
@@ -73,13 +247,208 @@ mod tests {
         assert_eq!(color, Color::new(90, 34, 100));
     }
 
+    use rand::SeedableRng;
+    use crate::creature::CreatureRng;
+
+    const TEST_TOTAL_GENES: usize = 10_000;
+
+    // Counting how many bits differ between 2 Genomes
+    fn count_flipped_bits(a: &Genome, b: &Genome) -> u32 {
+        a.genes().iter()
+            .zip(b.genes().iter())
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum()
+    }
+
     #[test]
     fn mutate_genome() {
-        unimplemented!();
+        let mut rng = CreatureRng::seed_from_u64(42);
+        let original = Genome::from_byte_slice(&[0; TEST_TOTAL_GENES * 2]);
+
+        // Per bit: expected flipped bits = total bits * rate
+        let mut genome = original.clone();
+        genome.randomly_mutate(&mut rng, PointMutation::PerBit(0.01));
+
+        let total_bits = (TEST_TOTAL_GENES as u32 * GENE_BITS) as f64;
+        let observed_rate = count_flipped_bits(&original, &genome) as f64 / total_bits;
+        assert!((observed_rate - 0.01).abs() < 0.002, "observed per-bit rate {}", observed_rate);
+
+        // Per gene: expected mutated genes = total genes * rate, each with exactly 1 flipped bit
+        let mut genome = original.clone();
+        genome.randomly_mutate(&mut rng, PointMutation::PerGene(0.05));
+
+        let mutated_genes = genome.genes().iter().filter(|&&gene| gene != 0).count();
+        assert!(genome.genes().iter().all(|gene| gene.count_ones() <= 1));
+        assert_eq!(count_flipped_bits(&original, &genome), mutated_genes as u32);
+
+        let observed_rate = mutated_genes as f64 / TEST_TOTAL_GENES as f64;
+        assert!((observed_rate - 0.05).abs() < 0.01, "observed per-gene rate {}", observed_rate);
+
+        // Zero rate should leave the Genome untouched
+        let mut genome = original.clone();
+        genome.randomly_mutate(&mut rng, PointMutation::PerBit(0.0));
+        assert_eq!(genome, original);
     }
 
     #[test]
     fn replicate_genome() {
-        unimplemented!();
+        let bytes: Vec<u8> = (0..TEST_TOTAL_GENES * 2).map(|i| (i % 251) as u8).collect();
+        let parent = Genome::from_byte_slice(&bytes);
+
+        // No mutation means a perfect copy
+        let mut rng = CreatureRng::seed_from_u64(7);
+        let offspring = parent.replicate(&mut rng, PointMutation::PerGene(0.0));
+        assert_eq!(offspring, parent);
+
+        let offspring = parent.replicate(&mut rng, PointMutation::PerGene(0.1));
+        assert_eq!(offspring.genes().len(), parent.genes().len());
+        assert_eq!(parent, Genome::from_byte_slice(&bytes));
+
+        let mutated_genes = parent.genes().iter()
+            .zip(offspring.genes().iter())
+            .filter(|(x, y)| x != y)
+            .count();
+        let observed_rate = mutated_genes as f64 / TEST_TOTAL_GENES as f64;
+        assert!((observed_rate - 0.1).abs() < 0.015, "observed per-gene rate {}", observed_rate);
+
+        // Same seed, same offspring
+        let mut rng_a = CreatureRng::seed_from_u64(99);
+        let mut rng_b = CreatureRng::seed_from_u64(99);
+        assert_eq!(
+            parent.replicate(&mut rng_a, PointMutation::PerBit(0.01)),
+            parent.replicate(&mut rng_b, PointMutation::PerBit(0.01))
+        );
+    }
+
+    #[test]
+    fn genome_hex_round_trip() {
+        let bytes: [u8; 6] = [100, 34, 90, 210, 0, 255];
+        let genome = Genome::from_byte_slice(&bytes);
+
+        assert_eq!(genome.to_hex(), "64225ad200ff");
+        assert_eq!(Genome::from_hex("64225ad200ff").unwrap(), genome);
+        assert_eq!(Genome::from_hex("64225AD200FF").unwrap(), genome);
+
+        let mut rng = CreatureRng::seed_from_u64(5);
+        let genes: Vec<Gene> = (0..100).map(|_| rng.gen()).collect();
+        let genome = Genome::from_genes(genes);
+        assert_eq!(Genome::from_hex(&genome.to_hex()).unwrap(), genome);
+
+        assert!(matches!(Genome::from_hex("64225"), Err(GenomeError::InvalidHexLength(5))));
+        assert!(matches!(Genome::from_hex("6422zz00"), Err(GenomeError::InvalidHexDigit(4))));
+    }
+
+    #[test]
+    fn color_from_short_genome() {
+        let genome = Genome::from_byte_slice(&[100, 34]);
+        assert_eq!(genome.generate_color().unwrap(), Color::new(0, 34, 100));
+
+        let genome = Genome::from_byte_slice(&[]);
+        assert!(matches!(genome.generate_color(), Err(GenomeError::EmptyGenome)));
+    }
+
+    #[test]
+    fn mutate_genome_length() {
+        let mut rng = CreatureRng::seed_from_u64(3);
+        let mutation = LengthMutation {
+            insertion_rate: 0.5,
+            deletion_rate: 0.5,
+            min_genes: 4,
+            max_genes: 12
+        };
+
+        let mut genome = Genome(vec![0; 8]);
+        let mut seen_lengths = std::collections::HashSet::new();
+        for _ in 0..500 {
+            genome.mutate_length(&mut rng, mutation);
+            assert!((4..=12).contains(&genome.genes().len()));
+            seen_lengths.insert(genome.genes().len());
+        }
+
+        // Both bounds should have been reached at some point
+        assert!(seen_lengths.contains(&4));
+        assert!(seen_lengths.contains(&12));
+
+        // Only insertion grows the genome by exactly 1 Gene, keeping the original genes in order
+        let mut genome = Genome(vec![0; 8]);
+        genome.mutate_length(&mut rng, LengthMutation { insertion_rate: 1.0, deletion_rate: 0.0, ..mutation });
+        assert_eq!(genome.genes().len(), 9);
+        assert!(genome.genes().iter().filter(|&&gene| gene == 0).count() >= 8);
+
+        // Genome never shrinks to nothing, even when allowed to
+        let mut genome = Genome(vec![0; 2]);
+        for _ in 0..10 {
+            genome.mutate_length(&mut rng, LengthMutation { insertion_rate: 0.0, deletion_rate: 1.0, min_genes: 0, ..mutation });
+        }
+        assert_eq!(genome.genes().len(), 1);
+
+        // Out of bounds Genomes are brought back within bounds, whatever the rates
+        let no_rates = LengthMutation { insertion_rate: 0.0, deletion_rate: 0.0, ..mutation };
+        let mut genome = Genome(vec![7; 20]);
+        genome.mutate_length(&mut rng, no_rates);
+        assert_eq!(genome.genes(), &vec![7; 12]);
+
+        let mut genome = Genome(vec![7; 2]);
+        genome.mutate_length(&mut rng, no_rates);
+        assert_eq!(genome.genes().len(), 4);
+        assert_eq!(genome.genes()[..2], [7, 7]);
+    }
+
+    #[test]
+    fn crossover_genomes() {
+        let mut rng = CreatureRng::seed_from_u64(1);
+        let parent_a = Genome(vec![0; 8]);
+        let parent_b = Genome(vec![0xFFFF; 8]);
+
+        // Single point: a run of parent A's genes followed by parent B's
+        for _ in 0..20 {
+            let child = parent_a.crossover(&parent_b, &mut rng, Crossover::SinglePoint);
+            let point = child.genes().iter().position(|&gene| gene == 0xFFFF).unwrap_or(8);
+            assert_eq!(child.genes().len(), 8);
+            assert!(child.genes()[..point].iter().all(|&gene| gene == 0));
+            assert!(child.genes()[point..].iter().all(|&gene| gene == 0xFFFF));
+        }
+
+        // Two point: parent B's genes are only found in one contiguous segment
+        for _ in 0..20 {
+            let child = parent_a.crossover(&parent_b, &mut rng, Crossover::TwoPoint);
+            let segments = child.genes()
+                .windows(2)
+                .filter(|pair| pair[0] != pair[1])
+                .count();
+            assert_eq!(child.genes().len(), 8);
+            assert!(segments <= 2);
+        }
+
+        // Uniform: every gene comes from either parent
+        let child = parent_a.crossover(&parent_b, &mut rng, Crossover::Uniform);
+        assert!(child.genes().iter().all(|&gene| gene == 0 || gene == 0xFFFF));
+    }
+
+    #[test]
+    fn crossover_different_lengths() {
+        let mut rng = CreatureRng::seed_from_u64(2);
+        let short = Genome(vec![1; 3]);
+        let long = Genome(vec![2; 10]);
+
+        for crossover in [Crossover::SinglePoint, Crossover::TwoPoint, Crossover::Uniform] {
+            for _ in 0..20 {
+                let child = short.crossover(&long, &mut rng, crossover);
+                assert!(child.genes().len() == 3 || child.genes().len() == 10);
+
+                let child = long.crossover(&short, &mut rng, crossover);
+                assert!(child.genes().len() == 3 || child.genes().len() == 10);
+            }
+        }
+    }
+
+    #[test]
+    fn genome_similarity() {
+        let genome = Genome(vec![0; 4]);
+
+        assert_eq!(genome.similarity(&genome), 1.0);
+        assert_eq!(genome.similarity(&Genome(vec![0xFFFF; 4])), 0.0);
+        assert_eq!(genome.similarity(&Genome(vec![0x00FF; 2])), 0.5);
+        assert_eq!(genome.similarity(&Genome(vec![])), 0.0);
     }
 }
@@ -0,0 +1,187 @@
+use crate::creature::CreatureID;
+use crate::vector2d::Vector2D;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Creature(CreatureID),
+    // Nothing can go through, or stand on a barrier
+    Barrier
+}
+
+// Dense, row-major map of what sits on each cell of the field
+#[derive(Debug, Clone)]
+pub struct OccupancyGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>
+}
+
+impl OccupancyGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Empty; width * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_in_bounds(&self, pos: &Vector2D<usize>) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+
+    pub fn position_to_index(&self, pos: &Vector2D<usize>) -> Option<usize> {
+        match self.is_in_bounds(pos) {
+            true => Some(pos.x + pos.y * self.width),
+            false => None
+        }
+    }
+
+    pub fn index_to_position(&self, index: usize) -> Vector2D<usize> {
+        Vector2D::new(index % self.width, index / self.width)
+    }
+
+    // Returns None if outside the grid
+    pub fn cell(&self, pos: &Vector2D<usize>) -> Option<Cell> {
+        self.position_to_index(pos).map(|i| self.cells[i])
+    }
+
+    // Returns None if the cell is empty, a barrier, OR outside the grid
+    pub fn occupant(&self, pos: &Vector2D<usize>) -> Option<CreatureID> {
+        match self.cell(pos)? {
+            Cell::Creature(id) => Some(id),
+            Cell::Empty | Cell::Barrier => None
+        }
+    }
+
+    // Creatures and barriers both occupy a cell
+    // Returns None if outside the grid
+    pub fn is_occupied(&self, pos: &Vector2D<usize>) -> Option<bool> {
+        self.cell(pos).map(|cell| cell != Cell::Empty)
+    }
+
+    pub fn is_barrier(&self, pos: &Vector2D<usize>) -> bool {
+        self.cell(pos) == Some(Cell::Barrier)
+    }
+
+    // Writing outside the grid is silently ignored
+    // Barriers are never overwritten
+    pub fn set(&mut self, pos: &Vector2D<usize>, occupant: Option<CreatureID>) {
+        if let Some(i) = self.position_to_index(pos) {
+            if self.cells[i] != Cell::Barrier {
+                self.cells[i] = occupant.map_or(Cell::Empty, Cell::Creature);
+            }
+        }
+    }
+
+    // Any creature on that cell is dropped from the grid
+    pub fn set_barrier(&mut self, pos: &Vector2D<usize>) {
+        if let Some(i) = self.position_to_index(pos) {
+            self.cells[i] = Cell::Barrier;
+        }
+    }
+
+    pub fn barriers(&self) -> impl Iterator<Item = Vector2D<usize>> + '_ {
+        (0..self.cells.len())
+            .filter(|&i| self.cells[i] == Cell::Barrier)
+            .map(|i| self.index_to_position(i))
+    }
+
+    // Move whoever sits on `old` to `new`
+    pub fn move_occupant(&mut self, old: &Vector2D<usize>, new: &Vector2D<usize>) {
+        let occupant = self.occupant(old);
+        self.set(old, None);
+        self.set(new, occupant);
+    }
+
+    // Removes every creature, barriers stay
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            if *cell != Cell::Barrier {
+                *cell = Cell::Empty;
+            }
+        }
+    }
+
+    // Number of cells taken by creatures
+    pub fn total_occupied(&self) -> usize {
+        self.cells.iter().filter(|cell| matches!(cell, Cell::Creature(_))).count()
+    }
+
+    pub fn total_barriers(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell == Cell::Barrier).count()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_occupant() {
+        let mut grid = OccupancyGrid::new(10, 5);
+        grid.set(&Vector2D::new(3, 4), Some(7));
+
+        assert_eq!(grid.occupant(&Vector2D::new(3, 4)), Some(7));
+        assert_eq!(grid.is_occupied(&Vector2D::new(3, 4)), Some(true));
+        assert_eq!(grid.is_occupied(&Vector2D::new(4, 3)), Some(false));
+        assert_eq!(grid.is_occupied(&Vector2D::new(10, 0)), None);
+        assert_eq!(grid.is_occupied(&Vector2D::new(0, 5)), None);
+
+        // Out of bounds writes are ignored
+        grid.set(&Vector2D::new(10, 10), Some(1));
+        assert_eq!(grid.total_occupied(), 1);
+    }
+
+    #[test]
+    fn move_occupant_around() {
+        let mut grid = OccupancyGrid::new(4, 4);
+        grid.set(&Vector2D::new(0, 0), Some(2));
+        grid.move_occupant(&Vector2D::new(0, 0), &Vector2D::new(1, 0));
+
+        assert_eq!(grid.occupant(&Vector2D::new(0, 0)), None);
+        assert_eq!(grid.occupant(&Vector2D::new(1, 0)), Some(2));
+
+        grid.clear();
+        assert_eq!(grid.total_occupied(), 0);
+    }
+
+    #[test]
+    fn barriers_stay_put() {
+        let mut grid = OccupancyGrid::new(4, 4);
+        grid.set_barrier(&Vector2D::new(1, 2));
+        grid.set(&Vector2D::new(1, 2), Some(3));
+        grid.set(&Vector2D::new(0, 0), Some(1));
+
+        assert!(grid.is_barrier(&Vector2D::new(1, 2)));
+        assert_eq!(grid.is_occupied(&Vector2D::new(1, 2)), Some(true));
+        assert_eq!(grid.occupant(&Vector2D::new(1, 2)), None);
+        assert_eq!(grid.total_occupied(), 1);
+
+        grid.clear();
+        assert_eq!(grid.total_occupied(), 0);
+        assert_eq!(grid.barriers().collect::<Vec<_>>(), vec![Vector2D::new(1, 2)]);
+        assert_eq!(grid.total_barriers(), 1);
+    }
+
+    #[test]
+    fn convert_index_and_position() {
+        let grid = OccupancyGrid::new(7, 3);
+
+        for i in 0..21 {
+            let pos = grid.index_to_position(i);
+            assert_eq!(grid.position_to_index(&pos), Some(i));
+        }
+        assert_eq!(grid.index_to_position(8), Vector2D::new(1, 1));
+    }
+}
@@ -1,51 +1,86 @@
 use std::io::{BufWriter, Write};
-use std::fs::File;
+use std::fs::{self, File};
+use std::path::Path;
 use std::error::Error;
+use std::env;
 
 mod simulation;
+mod barrier;
+mod checkpoint;
+mod config_file;
 mod creature;
 mod genome;
+mod grid;
+mod map_file;
 mod neuron;
+mod population_file;
 mod renderer;
+mod reproduction;
+mod selection;
+mod topology;
 mod vector2d;
 
 use neuron::{Connection, ConnectionType};
 use simulation::Simulation;
-use renderer::{RendererBuilder, Color, Buffer};
-
-const FIELD_WIDTH: usize = 50;
-const FIELD_HEIGHT: usize = 50;
+use renderer::{RendererBuilder, Buffer};
 
 
+// Usage: biosim_rust [config file]
+// Without a config file, the defaults in `config_file` are used
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut sim = Simulation::new(FIELD_WIDTH, FIELD_HEIGHT, 300, [0; 32], 8);
-
-    let gray = Color::new(0xaa, 0xaa, 0xaa);
-    let light_orange = Color::new(0xff, 0xdd, 0x8c);
+    let config = match env::args().nth(1) {
+        Some(path) => config_file::load_config(path)?,
+        None => config_file::parse_config("")?
+    };
+
+    let output_dir = config.output_dir;
+    let mut sim = match &config.load_checkpoint {
+        Some(path) => Simulation::load_checkpoint_file(config.simulation, path)?,
+        None => Simulation::new(config.simulation)
+    };
+
+    let mut renderer_builder = RendererBuilder::new()
+        .with_field_color(config.field_color)
+        .with_border_color(config.border_color)
+        .with_field_dimensions(sim.config().field_width, sim.config().field_height);
+    if let Some(color) = config.barrier_color {
+        renderer_builder = renderer_builder.with_barrier_color(color);
+    }
 
-    let mut renderer = RendererBuilder::new()
-        .with_field_color(light_orange)
-        .with_border_color(gray)
-        .with_field_dimensions(FIELD_WIDTH, FIELD_HEIGHT)
-        .build()?;
+    let mut renderer = renderer_builder.build()?;
 
     renderer.init()?;
-    sim.init()?;
+    match (&config.load_checkpoint, &config.population_file) {
+        (Some(_), _) => (),
+        (None, Some(path)) => sim.init_from_population(population_file::load_population(path)?)?,
+        (None, None) => sim.init()?
+    }
+    fs::create_dir_all(&output_dir)?;
+
+    for _ in 0..config.total_generations {
+        let total_survivors = sim.run_generation()?;
+        println!("Generation #{}: {} survivors", sim.generation(), total_survivors);
+    }
+
+    if let Some(path) = &config.save_checkpoint {
+        sim.save_checkpoint_file(path)?;
+    }
 
-    for i in 0..20 {
+    for i in 0..config.rendered_steps {
         sim.step();
         let raw_image_buffer = renderer.render(&sim)?;
         let (buffer_width, buffer_height) = renderer.buffer_dimensions();
-        export_to_tga(raw_image_buffer, buffer_width, buffer_height, i)?;
+        export_to_tga(&output_dir, raw_image_buffer, buffer_width, buffer_height, i)?;
     }
 
-    export_creatures_brain(&sim)?;
+    export_creatures_brain(&output_dir, &sim)?;
+    population_file::save_population(output_dir.join("population.txt"), &sim)?;
 
     Ok(())
 }
 
-fn export_creatures_brain(sim: &Simulation) -> Result<(), Box<dyn Error>> {
-    let mut file_writer = BufWriter::new(File::create("./output/brain.txt")?);
+fn export_creatures_brain(output_dir: &Path, sim: &Simulation) -> Result<(), Box<dyn Error>> {
+    let mut file_writer = BufWriter::new(File::create(output_dir.join("brain.txt"))?);
     let mut parsed_conns: String = "".to_string();
 
     for (i, creature) in sim.creatures().iter().enumerate() {
@@ -75,8 +110,8 @@ fn parse_connection(conn: &Connection) -> String {
     }
 }
 
-fn export_to_tga(buffer: Buffer, buffer_width: usize, buffer_height: usize, index: usize) -> Result<(), Box<dyn Error>> {
-    let mut file_writer = BufWriter::new(File::create(format!("./output/test{}.tga", index))?);
+fn export_to_tga(output_dir: &Path, buffer: Buffer, buffer_width: usize, buffer_height: usize, index: usize) -> Result<(), Box<dyn Error>> {
+    let mut file_writer = BufWriter::new(File::create(output_dir.join(format!("test{}.tga", index)))?);
     let mut header_data: [u8; 18] = [0; 18];
 
     // Image type: uncompressed true-color
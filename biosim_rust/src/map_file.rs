@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::vector2d::Vector2D;
+
+// ASCII map format, one character per cell, one row per line:
+//
+// ##########
+// #0..##..1#
+// #...##...#
+// #SS....SS#
+// ##########
+//
+// - `#` barrier
+// - `.` free cell
+// - `S` safe zone; creatures standing there survive selection
+// - `0` to `9` spawn regions; creatures only spawn on those, split evenly between regions
+// Every cell that isn't a barrier is free to walk on
+// Every row should be as wide as the first one; trailing empty lines are ignored
+#[derive(Debug)]
+pub struct ArenaMap {
+    pub width: usize,
+    pub height: usize,
+    pub barriers: Vec<Vector2D<usize>>,
+    pub safe_zone: HashSet<Vector2D<usize>>,
+    // Region digit => its cells
+    pub spawn_regions: BTreeMap<u8, Vec<Vector2D<usize>>>
+}
+
+#[derive(Debug, Error)]
+pub enum MapFileError {
+    #[error("Couldn't read map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Map is empty")]
+    EmptyMap,
+    #[error("Line {0}, column {1}: unexpected `{2}` (expected `#`, `.`, `S` or a digit)")]
+    InvalidCell(usize, usize, char),
+    #[error("Line {0}, column {1}: row is {2} cells wide, but the first row is {3}")]
+    RaggedRow(usize, usize, usize, usize),
+}
+
+pub fn parse_map(text: &str) -> Result<ArenaMap, MapFileError> {
+    let rows: Vec<&str> = text.trim_end().lines().map(|line| line.trim_end()).collect();
+    let width = rows.first().map_or(0, |row| row.chars().count());
+    if width == 0 {
+        return Err(MapFileError::EmptyMap);
+    }
+
+    let mut map = ArenaMap {
+        width,
+        height: rows.len(),
+        barriers: vec![],
+        safe_zone: HashSet::new(),
+        spawn_regions: BTreeMap::new()
+    };
+
+    for (y, row) in rows.iter().enumerate() {
+        let line_number = y + 1;
+        let row_width = row.chars().count();
+        if row_width != width {
+            return Err(MapFileError::RaggedRow(line_number, row_width.min(width) + 1, row_width, width));
+        }
+
+        for (x, cell) in row.chars().enumerate() {
+            let pos = Vector2D::new(x, y);
+            match cell {
+                '#' => map.barriers.push(pos),
+                '.' => {},
+                'S' => { map.safe_zone.insert(pos); },
+                '0'..='9' => map.spawn_regions.entry(cell as u8 - b'0').or_default().push(pos),
+                _ => return Err(MapFileError::InvalidCell(line_number, x + 1, cell))
+            }
+        }
+    }
+
+    Ok(map)
+}
+
+pub fn load_map<P: AsRef<Path>>(path: P) -> Result<ArenaMap, MapFileError> {
+    parse_map(&fs::read_to_string(path)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_map_file() {
+        let text = "\
+#####
+#0.1#
+#SS0#
+#####
+
+";
+        let map = parse_map(text).unwrap();
+
+        assert_eq!((map.width, map.height), (5, 4));
+        assert_eq!(map.barriers.len(), 5 * 2 + 2 * 2);
+        assert!(map.barriers.contains(&Vector2D::new(4, 2)));
+        assert_eq!(map.safe_zone, HashSet::from([Vector2D::new(1, 2), Vector2D::new(2, 2)]));
+        assert_eq!(map.spawn_regions[&0], vec![Vector2D::new(1, 1), Vector2D::new(3, 2)]);
+        assert_eq!(map.spawn_regions[&1], vec![Vector2D::new(3, 1)]);
+    }
+
+    #[test]
+    fn map_file_errors() {
+        let error_message = |text: &str| parse_map(text).err().unwrap().to_string();
+
+        assert_eq!(error_message("\n\n"), "Map is empty");
+        assert_eq!(
+            error_message("####\n#.x#\n####"),
+            "Line 2, column 3: unexpected `x` (expected `#`, `.`, `S` or a digit)"
+        );
+        assert_eq!(error_message("####\n#..\n####"), "Line 2, column 4: row is 3 cells wide, but the first row is 4");
+        assert_eq!(error_message("####\n#...#"), "Line 2, column 5: row is 5 cells wide, but the first row is 4");
+    }
+}
@@ -10,5 +10,10 @@ pub enum ActionNeuron {
     MoveNorth,
     MoveSouth,
     MoveEast,
-    MoveWest
+    MoveWest,
+    MoveForward,
+    MoveReverse,
+    MoveLeft,
+    MoveRight,
+    MoveRandom
 }
@@ -0,0 +1,116 @@
+use super::{Connection, ConnectionType, InternalNeuron};
+use super::sensory_neuron::SensoryNeuron;
+use super::action_neuron::ActionNeuron;
+
+// Connections of one ConnectionType, as parallel arrays
+// Sources/sinks are SensoryNeuron IDs, ActionNeuron IDs or InternalNeuronIDs, depending on the type
+#[derive(Debug, Default)]
+struct Segment {
+    sources: Vec<u32>,
+    sinks: Vec<u32>,
+    weights: Vec<f64>,
+}
+
+impl Segment {
+    fn push(&mut self, source: usize, sink: usize, weight: f64) {
+        self.sources.push(source as u32);
+        self.sinks.push(sink as u32);
+        self.weights.push(weight);
+    }
+
+    // (source, sink, weight) of every connection, in the original order
+    fn iter(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.sources.iter()
+            .zip(&self.sinks)
+            .zip(&self.weights)
+            .map(|((&source, &sink), &weight)| (source as usize, sink as usize, weight))
+    }
+}
+
+// Brain connections flattened for evaluation: no enum matching nor HashMap lookups while thinking
+// Sensor and action values are plain slices indexed by neuron ID
+// Connections keep their order within a type, so the sums come out exactly like walking
+// the sorted Vec<Connection> one by one
+#[derive(Debug, Default)]
+pub struct BrainPlan {
+    // Neurons actually connected, sorted by ID; only these need to be sensed/acted on
+    sensors: Vec<SensoryNeuron>,
+    actions: Vec<ActionNeuron>,
+
+    sensory_to_action: Segment,
+    sensory_to_internal: Segment,
+    internal_to_internal: Segment,
+    internal_to_action: Segment,
+}
+
+impl BrainPlan {
+    pub fn compile(connections: &[Connection]) -> Self {
+        let mut plan = Self::default();
+
+        for conn in connections {
+            let weight = conn.weight();
+            match *conn.connection_type() {
+                ConnectionType::SensoryToAction { source, sink } => {
+                    plan.sensors.push(source);
+                    plan.actions.push(sink);
+                    plan.sensory_to_action.push(source as usize, sink as usize, weight);
+                }
+
+                ConnectionType::SensoryToInternal { source, sink } => {
+                    plan.sensors.push(source);
+                    plan.sensory_to_internal.push(source as usize, sink, weight);
+                }
+
+                ConnectionType::InternalToInternal { source, sink } => {
+                    plan.internal_to_internal.push(source, sink, weight);
+                }
+
+                ConnectionType::InternalToAction { source, sink } => {
+                    plan.actions.push(sink);
+                    plan.internal_to_action.push(source, sink as usize, weight);
+                }
+            }
+        }
+
+        plan.sensors.sort_by_key(|&neuron| neuron as usize);
+        plan.sensors.dedup();
+        plan.actions.sort_by_key(|&neuron| neuron as usize);
+        plan.actions.dedup();
+
+        plan
+    }
+
+    pub fn sensors(&self) -> &[SensoryNeuron] {
+        &self.sensors
+    }
+
+    pub fn actions(&self) -> &[ActionNeuron] {
+        &self.actions
+    }
+
+    // Same lifecycle as described in `Brain::think`
+    pub fn run(&self, internal_neurons: &mut [InternalNeuron], decay: f64, sensor_values: &[f64], action_values: &mut [f64]) {
+        action_values.fill(0.0);
+
+        for (source, sink, weight) in self.sensory_to_action.iter() {
+            action_values[sink] += sensor_values[source] * weight;
+        }
+
+        for (source, sink, weight) in self.sensory_to_internal.iter() {
+            internal_neurons[sink].accumulate(sensor_values[source] * weight);
+        }
+
+        for (source, sink, weight) in self.internal_to_internal.iter() {
+            let value = internal_neurons[source].state() * weight;
+            internal_neurons[sink].accumulate(value);
+        }
+
+        for neuron in internal_neurons.iter_mut() {
+            neuron.activate(decay);
+        }
+
+        for (source, sink, weight) in self.internal_to_action.iter() {
+            action_values[sink] += internal_neurons[source].state() * weight;
+        }
+    }
+}
@@ -0,0 +1,329 @@
+#[cfg(test)]
+use thiserror::Error;
+
+use crate::genome::Gene;
+use super::{Connection, ConnectionType};
+#[cfg(test)]
+use super::InternalNeuronID;
+use super::internal_neuron::{Activation, TOTAL_ACTIVATION_VARIANT};
+use super::sensory_neuron::{SensoryNeuron, TOTAL_SENSORY_NEURON_VARIANT};
+use super::action_neuron::{ActionNeuron, TOTAL_ACTION_NEURON_VARIANT};
+
+// Narrow Gene bit layout, bit 15 being the most significant:
+//
+// 15 14 | 13 12 11 10 9 | 8 7 6 5 4 | 3 2 1 0
+// type  | source ID     | sink ID   | weight + 8
+//
+// - type: 0 SensoryToAction, 1 SensoryToInternal, 2 InternalToInternal, 3 InternalToAction
+// - IDs wrap around the number of neurons of their kind when decoding
+// - weight is an integer from -8 to 7
+const NARROW_TYPE_SHIFT: u32 = 14;
+const NARROW_SOURCE_SHIFT: u32 = 9;
+const NARROW_SINK_SHIFT: u32 = 4;
+pub(super) const NARROW_ID_BITS: u32 = 5;
+const NARROW_WEIGHT_MASK: Gene = 0xF;
+const NARROW_WEIGHT_OFFSET: i32 = 8;
+
+// Wide layout, over 2 Genes:
+//
+// First Gene:  15 14 | 13 ... 7   | 6 ... 0
+//              type  | source ID  | sink ID
+// Second Gene: weight, a 16-bit signed integer scaled down to [-4.0, 4.0) in 1/8192 steps
+const WIDE_TYPE_SHIFT: u32 = 14;
+const WIDE_SOURCE_SHIFT: u32 = 7;
+pub(super) const WIDE_ID_BITS: u32 = 7;
+const WIDE_WEIGHT_SCALE: f64 = 8192.0;
+
+// Activation Genes, only with `BrainConfig::activation_from_genome`: the first
+// `activation_genes(total_internal_neurons)` Genes of the genome hold internal neuron
+// activations, and are not decoded into Connections
+//
+// 15     | 14 13 12 | 11 10 9 | 8 7 6 | 5 4 3 | 2 1 0
+// unused | neuron 4 | neuron 3 | neuron 2 | neuron 1 | neuron 0
+//
+// - the Nth Gene holds neurons 5N to 5N + 4
+// - each field is an Activation ID, wrapped around the number of Activations
+const ACTIVATION_BITS: u32 = 3;
+const ACTIVATIONS_PER_GENE: usize = 5;
+
+// Encoding only serves to check decoding in tests: Genes are never written back from Connections
+#[cfg(test)]
+#[derive(Debug, Error, PartialEq)]
+pub enum GeneCodecError {
+    #[error("Neuron ID {0} doesn't fit in {1} bits")]
+    IdOutOfRange(usize, u32),
+    #[error("Weight {0} can't be encoded exactly")]
+    UnrepresentableWeight(f64),
+}
+
+// InternalNeuronIDs are wrapped around `total_internal_neurons`, which must not be 0
+pub fn decode(gene: Gene, total_internal_neurons: usize) -> Connection {
+    let id_mask = (1 << NARROW_ID_BITS) - 1;
+    let weight = (gene & NARROW_WEIGHT_MASK) as i32 - NARROW_WEIGHT_OFFSET;
+
+    Connection {
+        connection_type: decode_connection_type(
+            gene >> NARROW_TYPE_SHIFT,
+            ((gene >> NARROW_SOURCE_SHIFT) & id_mask) as usize,
+            ((gene >> NARROW_SINK_SHIFT) & id_mask) as usize,
+            total_internal_neurons
+        ),
+        weight: weight.into()
+    }
+}
+
+#[cfg(test)]
+pub fn encode(conn: &Connection) -> Result<Gene, GeneCodecError> {
+    let (type_id, source_id, sink_id) = encode_connection_type(&conn.connection_type, NARROW_ID_BITS)?;
+
+    let weight = conn.weight as i32;
+    let offset_weight = weight + NARROW_WEIGHT_OFFSET;
+    if weight as f64 != conn.weight || !(0..=NARROW_WEIGHT_MASK as i32).contains(&offset_weight) {
+        return Err(GeneCodecError::UnrepresentableWeight(conn.weight));
+    }
+
+    Ok(
+        type_id << NARROW_TYPE_SHIFT
+            | source_id << NARROW_SOURCE_SHIFT
+            | sink_id << NARROW_SINK_SHIFT
+            | offset_weight as Gene
+    )
+}
+
+pub fn decode_wide(genes: [Gene; 2], total_internal_neurons: usize) -> Connection {
+    let [ids, weight] = genes;
+    let id_mask = (1 << WIDE_ID_BITS) - 1;
+
+    Connection {
+        connection_type: decode_connection_type(
+            ids >> WIDE_TYPE_SHIFT,
+            ((ids >> WIDE_SOURCE_SHIFT) & id_mask) as usize,
+            (ids & id_mask) as usize,
+            total_internal_neurons
+        ),
+        weight: weight as i16 as f64 / WIDE_WEIGHT_SCALE
+    }
+}
+
+#[cfg(test)]
+pub fn encode_wide(conn: &Connection) -> Result<[Gene; 2], GeneCodecError> {
+    let (type_id, source_id, sink_id) = encode_connection_type(&conn.connection_type, WIDE_ID_BITS)?;
+
+    let scaled_weight = conn.weight * WIDE_WEIGHT_SCALE;
+    let weight = scaled_weight as i16;
+    if weight as f64 != scaled_weight {
+        return Err(GeneCodecError::UnrepresentableWeight(conn.weight));
+    }
+
+    Ok([type_id << WIDE_TYPE_SHIFT | source_id << WIDE_SOURCE_SHIFT | sink_id, weight as Gene])
+}
+
+pub fn activation_genes(total_internal_neurons: usize) -> usize {
+    total_internal_neurons.div_ceil(ACTIVATIONS_PER_GENE)
+}
+
+// Neurons whose Gene is missing (the genome is too short) get `fallback`
+pub fn decode_activations(genes: &[Gene], total_internal_neurons: usize, fallback: Activation) -> Vec<Activation> {
+    let mask = (1 << ACTIVATION_BITS) - 1;
+
+    (0..total_internal_neurons)
+        .map(|neuron| match genes.get(neuron / ACTIVATIONS_PER_GENE) {
+            Some(gene) => {
+                let id = (gene >> (ACTIVATION_BITS as usize * (neuron % ACTIVATIONS_PER_GENE))) & mask;
+                Activation::from_id(id as usize % TOTAL_ACTIVATION_VARIANT).unwrap()
+            }
+            None => fallback
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub fn encode_activations(activations: &[Activation]) -> Vec<Gene> {
+    activations.chunks(ACTIVATIONS_PER_GENE)
+        .map(|chunk| chunk.iter()
+            .enumerate()
+            .fold(0, |gene, (i, &activation)| gene | (activation as Gene) << (ACTIVATION_BITS as usize * i)))
+        .collect()
+}
+
+fn decode_connection_type(type_id: Gene, source_id: usize, sink_id: usize, total_internal_neurons: usize) -> ConnectionType {
+    let sensory = |id: usize| SensoryNeuron::from_id(id % TOTAL_SENSORY_NEURON_VARIANT).unwrap();
+    let action = |id: usize| ActionNeuron::from_id(id % TOTAL_ACTION_NEURON_VARIANT).unwrap();
+    let internal = |id: usize| id % total_internal_neurons;
+
+    match type_id {
+        0 => ConnectionType::SensoryToAction { source: sensory(source_id), sink: action(sink_id) },
+        1 => ConnectionType::SensoryToInternal { source: sensory(source_id), sink: internal(sink_id) },
+        2 => ConnectionType::InternalToInternal { source: internal(source_id), sink: internal(sink_id) },
+        3 => ConnectionType::InternalToAction { source: internal(source_id), sink: action(sink_id) },
+        _ => unreachable!("ConnectionType only takes 2 bits")
+    }
+}
+
+// (type ID, source ID, sink ID), with IDs checked against `id_bits`
+#[cfg(test)]
+fn encode_connection_type(connection_type: &ConnectionType, id_bits: u32) -> Result<(Gene, Gene, Gene), GeneCodecError> {
+    let (type_id, source_id, sink_id): (Gene, usize, InternalNeuronID) = match *connection_type {
+        ConnectionType::SensoryToAction { source, sink } => (0, source as usize, sink as usize),
+        ConnectionType::SensoryToInternal { source, sink } => (1, source as usize, sink),
+        ConnectionType::InternalToInternal { source, sink } => (2, source, sink),
+        ConnectionType::InternalToAction { source, sink } => (3, source, sink as usize),
+    };
+
+    let fit = |id: usize| match id < 1 << id_bits {
+        true => Ok(id as Gene),
+        false => Err(GeneCodecError::IdOutOfRange(id, id_bits))
+    };
+
+    Ok((type_id, fit(source_id)?, fit(sink_id)?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64;
+
+    use super::*;
+
+    // Pieces of the narrow layout, as masks
+    const NARROW_FIELDS: [Gene; 4] = [0b11 << 14, 0b11111 << 9, 0b11111 << 4, 0b1111];
+
+    fn random_connection<R: Rng>(rng: &mut R, total_internal_neurons: usize, weight: f64) -> Connection {
+        let sensory = SensoryNeuron::from_id(rng.gen_range(0..TOTAL_SENSORY_NEURON_VARIANT)).unwrap();
+        let action = ActionNeuron::from_id(rng.gen_range(0..TOTAL_ACTION_NEURON_VARIANT)).unwrap();
+        let internal = rng.gen_range(0..total_internal_neurons);
+        let other_internal = rng.gen_range(0..total_internal_neurons);
+
+        let connection_type = match rng.gen_range(0..4) {
+            0 => ConnectionType::SensoryToAction { source: sensory, sink: action },
+            1 => ConnectionType::SensoryToInternal { source: sensory, sink: internal },
+            2 => ConnectionType::InternalToInternal { source: internal, sink: other_internal },
+            _ => ConnectionType::InternalToAction { source: internal, sink: action },
+        };
+
+        Connection { connection_type, weight }
+    }
+
+    #[test]
+    fn narrow_fields_cover_every_bit_once() {
+        assert_eq!(NARROW_FIELDS.iter().fold(0, |acc, field| acc | field), Gene::MAX);
+        assert_eq!(NARROW_FIELDS.iter().map(|field| field.count_ones()).sum::<u32>(), Gene::BITS);
+
+        // Each field can be changed without touching the others
+        let total_internal_neurons = 32;
+        let base = decode(0, total_internal_neurons);
+        for field in NARROW_FIELDS {
+            let lowest_bit = field & field.wrapping_neg();
+            let conn = decode(lowest_bit, total_internal_neurons);
+            assert_eq!(encode(&conn), Ok(lowest_bit));
+            assert_ne!(conn, base);
+        }
+    }
+
+    #[test]
+    fn decode_known_genes() {
+        // type 1, source 3, sink 22, weight 12 - 8
+        let conn = decode(1 << 14 | 3 << 9 | 22 << 4 | 12, 4);
+        assert_eq!(conn.connection_type, ConnectionType::SensoryToInternal {
+            source: SensoryNeuron::from_id(3).unwrap(),
+            sink: 22 % 4
+        });
+        assert_eq!(conn.weight, 4.0);
+
+        // type 3, source 69, sink 5, weight -1.5
+        let conn = decode_wide([3 << 14 | 69 << 7 | 5, (-12288_i16) as Gene], 4);
+        assert_eq!(conn.connection_type, ConnectionType::InternalToAction {
+            source: 69 % 4,
+            sink: ActionNeuron::from_id(5).unwrap()
+        });
+        assert_eq!(conn.weight, -1.5);
+        assert_eq!(decode_wide([0, 0x7FFF], 4).weight, 32767.0 / 8192.0);
+        assert_eq!(decode_wide([0, 0x8000], 4).weight, -4.0);
+    }
+
+    #[test]
+    fn every_narrow_gene_round_trips() {
+        for total_internal_neurons in [1, 4, 32] {
+            for gene in 0..=Gene::MAX {
+                let conn = decode(gene, total_internal_neurons);
+                let encoded = encode(&conn).unwrap();
+                assert_eq!(decode(encoded, total_internal_neurons), conn);
+
+                // IDs are wrapped around when decoding, so a Gene only comes back unchanged
+                // if its IDs were already in range
+                let (source_total, sink_total) = match gene >> 14 {
+                    0 => (TOTAL_SENSORY_NEURON_VARIANT, TOTAL_ACTION_NEURON_VARIANT),
+                    1 => (TOTAL_SENSORY_NEURON_VARIANT, total_internal_neurons),
+                    2 => (total_internal_neurons, total_internal_neurons),
+                    _ => (total_internal_neurons, TOTAL_ACTION_NEURON_VARIANT)
+                };
+                let source_id = ((gene >> 9) & 0x1F) as usize;
+                let sink_id = ((gene >> 4) & 0x1F) as usize;
+                assert_eq!(encoded == gene, source_id < source_total && sink_id < sink_total, "gene {:016b}", gene);
+            }
+        }
+    }
+
+    #[test]
+    fn random_connections_round_trip() {
+        let mut rng = Pcg64::seed_from_u64(25);
+        for _ in 0..10_000 {
+            let total_internal_neurons = rng.gen_range(1..=32);
+            let weight = rng.gen_range(-8..=7) as f64;
+            let conn = random_connection(&mut rng, total_internal_neurons, weight);
+            assert_eq!(decode(encode(&conn).unwrap(), total_internal_neurons), conn);
+
+            let total_internal_neurons = rng.gen_range(1..=128);
+            let weight = rng.gen::<i16>() as f64 / WIDE_WEIGHT_SCALE;
+            let conn = random_connection(&mut rng, total_internal_neurons, weight);
+            assert_eq!(decode_wide(encode_wide(&conn).unwrap(), total_internal_neurons), conn);
+        }
+
+        // Every wide weight comes back unchanged
+        for _ in 0..10_000 {
+            let genes = [rng.gen::<Gene>(), rng.gen::<Gene>()];
+            assert_eq!(encode_wide(&decode_wide(genes, 128)).map(|[_, weight]| weight), Ok(genes[1]));
+        }
+    }
+
+    #[test]
+    fn activation_genes_round_trip() {
+        assert_eq!(activation_genes(1), 1);
+        assert_eq!(activation_genes(5), 1);
+        assert_eq!(activation_genes(6), 2);
+
+        let activations: Vec<Activation> = (0..12)
+            .map(|i| Activation::from_id(i % TOTAL_ACTIVATION_VARIANT).unwrap())
+            .collect();
+        let genes = encode_activations(&activations);
+        assert_eq!(genes.len(), activation_genes(activations.len()));
+        assert_eq!(decode_activations(&genes, activations.len(), Activation::Identity), activations);
+
+        // Out of range IDs wrap around, missing Genes fall back
+        let gene = 7 << 3 | Activation::Relu as Gene;
+        assert_eq!(
+            decode_activations(&[gene], 7, Activation::Identity),
+            [Activation::Relu, Activation::Sigmoid, Activation::Tanh, Activation::Tanh, Activation::Tanh,
+                Activation::Identity, Activation::Identity]
+        );
+
+        // Bit 15 is left alone
+        assert_eq!(decode_activations(&[1 << 15], 5, Activation::Identity), [Activation::Tanh; 5]);
+    }
+
+    #[test]
+    fn encoding_errors() {
+        let conn = |connection_type: ConnectionType, weight: f64| Connection { connection_type, weight };
+        let internal = |source: usize, sink: usize| ConnectionType::InternalToInternal { source, sink };
+
+        assert_eq!(encode(&conn(internal(32, 0), 1.0)), Err(GeneCodecError::IdOutOfRange(32, 5)));
+        assert_eq!(encode_wide(&conn(internal(0, 200), 1.0)), Err(GeneCodecError::IdOutOfRange(200, 7)));
+        assert!(encode_wide(&conn(internal(32, 0), 1.0)).is_ok());
+
+        assert_eq!(encode(&conn(internal(0, 0), 8.0)), Err(GeneCodecError::UnrepresentableWeight(8.0)));
+        assert_eq!(encode(&conn(internal(0, 0), 0.5)), Err(GeneCodecError::UnrepresentableWeight(0.5)));
+        assert_eq!(encode_wide(&conn(internal(0, 0), 4.0)), Err(GeneCodecError::UnrepresentableWeight(4.0)));
+        assert_eq!(encode_wide(&conn(internal(0, 0), 1e-5)), Err(GeneCodecError::UnrepresentableWeight(1e-5)));
+    }
+}
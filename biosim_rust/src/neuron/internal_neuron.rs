@@ -1,29 +1,139 @@
-use std::cmp::Ordering;
+use biosim_rust_macros::enum_from_id;
 
+pub const DEFAULT_LEAKY_RELU_SLOPE: f64 = 0.01;
 
+#[enum_from_id]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activation {
+    Tanh,
+    Sigmoid,
+    Relu,
+    LeakyRelu,
+    Identity,
+    // 1 if the input is positive, 0 otherwise
+    Step,
+}
+
+impl Activation {
+    pub fn apply(self, input: f64, leaky_relu_slope: f64) -> f64 {
+        match self {
+            Activation::Tanh => input.tanh(),
+            Activation::Sigmoid => 1.0 / (1.0 + (-input).exp()),
+            Activation::Relu => input.max(0.0),
+            Activation::LeakyRelu if input > 0.0 => input,
+            Activation::LeakyRelu => input * leaky_relu_slope,
+            Activation::Identity => input,
+            Activation::Step if input > 0.0 => 1.0,
+            Activation::Step => 0.0,
+        }
+    }
+}
+
+// Inputs are summed up during a step, then go through the activation once every input is in
 #[derive(Debug, Clone, Copy)]
 pub struct InternalNeuron {
-    state: f64
+    state: f64,
+    input: f64,
+    activation: Activation,
+    slope: f64
 }
 
 impl InternalNeuron {
-    pub fn new() -> Self {
+    // `slope` only matters for leaky ReLU
+    pub fn with_activation(activation: Activation, slope: f64) -> Self {
         Self {
-            state: 0.0
+            state: 0.0,
+            input: 0.0,
+            activation,
+            slope
         }
     }
 
-    // Uses Leaky ReLU
-    pub fn activation_func(&mut self, input: f64) -> () {
-        let value = match input.total_cmp(&0_f64) {
-            Ordering::Greater => input,
-            _ => input * 0.01
-        };
+    pub fn accumulate(&mut self, input: f64) {
+        self.input += input;
+    }
 
-        self.state = value;
+    // Turn the summed inputs into the new state, and start over for the next step
+    // `decay` is how much of the previous state is forgotten; anything below 1.0 feeds
+    // the rest back in as an extra input, as a short term memory
+    pub fn activate(&mut self, decay: f64) {
+        let input = self.input + self.state * (1.0 - decay);
+        self.state = self.activation.apply(input, self.slope);
+        self.input = 0.0;
+    }
+
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+        self.input = 0.0;
     }
 
     pub fn state(&self) -> f64 {
         self.state
     }
+
+    pub fn set_state(&mut self, state: f64) {
+        self.state = state;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activation_functions() {
+        let apply = |activation: Activation, input: f64| activation.apply(input, 0.1);
+
+        assert_eq!(apply(Activation::Tanh, 0.0), 0.0);
+        assert!((apply(Activation::Tanh, 1.0) - 0.7616).abs() < 1e-4);
+        assert_eq!(apply(Activation::Sigmoid, 0.0), 0.5);
+        assert_eq!(apply(Activation::Relu, -2.0), 0.0);
+        assert_eq!(apply(Activation::Relu, 2.0), 2.0);
+        assert_eq!(apply(Activation::LeakyRelu, -2.0), -0.2);
+        assert_eq!(apply(Activation::LeakyRelu, 2.0), 2.0);
+        assert_eq!(apply(Activation::Identity, -2.0), -2.0);
+        assert_eq!(apply(Activation::Step, 0.0), 0.0);
+        assert_eq!(apply(Activation::Step, 0.3), 1.0);
+    }
+
+    #[test]
+    fn sum_inputs_then_activate() {
+        let mut neuron = InternalNeuron::with_activation(Activation::Relu, DEFAULT_LEAKY_RELU_SLOPE);
+        neuron.accumulate(2.0);
+        neuron.accumulate(-3.0);
+        neuron.accumulate(0.5);
+        // Only the sum goes through ReLU; the 2.0 alone would have been let through
+        assert_eq!(neuron.state(), 0.0);
+        neuron.activate(1.0);
+        assert_eq!(neuron.state(), 0.0);
+
+        neuron.accumulate(1.5);
+        neuron.activate(1.0);
+        assert_eq!(neuron.state(), 1.5);
+    }
+
+    #[test]
+    fn decaying_state() {
+        let mut neuron = InternalNeuron::with_activation(Activation::Identity, DEFAULT_LEAKY_RELU_SLOPE);
+        neuron.accumulate(1.0);
+        neuron.activate(0.25);
+        assert_eq!(neuron.state(), 1.0);
+
+        // No input, the state fades away
+        neuron.activate(0.25);
+        assert_eq!(neuron.state(), 0.75);
+        neuron.activate(0.25);
+        assert_eq!(neuron.state(), 0.5625);
+
+        // Fully forgotten
+        neuron.activate(1.0);
+        assert_eq!(neuron.state(), 0.0);
+
+        neuron.accumulate(2.0);
+        neuron.activate(0.0);
+        neuron.reset();
+        neuron.activate(0.0);
+        assert_eq!(neuron.state(), 0.0);
+    }
 }
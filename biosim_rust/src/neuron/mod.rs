@@ -4,18 +4,74 @@ use std::collections::HashMap;
 pub mod internal_neuron;
 pub mod action_neuron;
 pub mod sensory_neuron;
+mod brain_plan;
+pub mod gene_codec;
 
-use crate::genome::{Gene, Genome};
-use sensory_neuron::{SensoryNeuron, TOTAL_SENSORY_NEURON_VARIANT};
-use action_neuron::{ActionNeuron, TOTAL_ACTION_NEURON_VARIANT};
-use internal_neuron::InternalNeuron;
+use crate::genome::Genome;
+use sensory_neuron::SensoryNeuron;
+use action_neuron::ActionNeuron;
+use internal_neuron::{Activation, InternalNeuron, DEFAULT_LEAKY_RELU_SLOPE};
+use brain_plan::BrainPlan;
 
 pub type InternalNeuronID = usize;
-const MAX_INTERNAL_NEURONS: usize = 4;
+pub const DEFAULT_INTERNAL_NEURONS: usize = 4;
+// Internal neurons forget their previous state every step, unless set lower
+pub const DEFAULT_NEURON_DECAY: f64 = 1.0;
+// How Genes are turned into Connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneFormat {
+    // One Gene per Connection, with a 4-bit integer weight (-8..7)
+    Narrow,
+    // Two consecutive Genes per Connection (32 bits), with wider IDs and a fine-grained weight
+    // A trailing odd Gene is ignored; inserting/deleting a Gene shifts every following
+    // Connection's frame, which is just another (harsh) mutation
+    Wide,
+}
+
+impl GeneFormat {
+    // Internal neurons a Gene can tell apart; any neuron past that would never be connected
+    pub fn max_internal_neurons(self) -> usize {
+        match self {
+            GeneFormat::Narrow => 1 << gene_codec::NARROW_ID_BITS,
+            GeneFormat::Wide => 1 << gene_codec::WIDE_ID_BITS,
+        }
+    }
+}
+
+// Knobs shared by every Brain in a Simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrainConfig {
+    pub total_internal_neurons: usize,
+    // Used by every internal neuron, unless `activation_from_genome` is set
+    pub activation: Activation,
+    // Each internal neuron gets its own activation, from dedicated genes at the start of the
+    // genome (see `gene_codec::decode_activations`)
+    pub activation_from_genome: bool,
+    pub leaky_relu_slope: f64,
+    // Part of an internal neuron's state lost between steps, from 0.0 (kept whole) to 1.0 (forgotten)
+    pub neuron_decay: f64,
+    pub gene_format: GeneFormat,
+}
+
+impl Default for BrainConfig {
+    fn default() -> Self {
+        Self {
+            total_internal_neurons: DEFAULT_INTERNAL_NEURONS,
+            activation: Activation::LeakyRelu,
+            activation_from_genome: false,
+            leaky_relu_slope: DEFAULT_LEAKY_RELU_SLOPE,
+            neuron_decay: DEFAULT_NEURON_DECAY,
+            gene_format: GeneFormat::Narrow
+        }
+    }
+}
 
 pub struct Brain {
     connections: Vec<Connection>,
+    // `connections`, compiled into what's actually run every step
+    plan: BrainPlan,
     internal_neurons: Vec<InternalNeuron>,
+    neuron_decay: f64,
 }
 
 impl Brain {
@@ -23,11 +79,34 @@ impl Brain {
         &self.connections
     }
 
-    pub fn from_genome(genome: &Genome) -> Self {
-        let mut connections: Vec<Connection> = genome.genes()
-            .iter()
-            .map(|gene| Connection::from_gene(*gene))
-            .collect();
+    pub fn internal_neurons(&self) -> &Vec<InternalNeuron> {
+        &self.internal_neurons
+    }
+
+    pub fn internal_neurons_mut(&mut self) -> &mut Vec<InternalNeuron> {
+        &mut self.internal_neurons
+    }
+
+    pub fn from_genome(genome: &Genome, config: BrainConfig) -> Self {
+        let total_internal_neurons = config.total_internal_neurons;
+        let (activation_genes, connection_genes) = match config.activation_from_genome {
+            true => {
+                let total_activation_genes = gene_codec::activation_genes(total_internal_neurons);
+                genome.genes().split_at(total_activation_genes.min(genome.genes().len()))
+            }
+            false => (&[][..], &genome.genes()[..])
+        };
+
+        let mut connections: Vec<Connection> = match config.gene_format {
+            GeneFormat::Narrow => connection_genes
+                .iter()
+                .map(|&gene| gene_codec::decode(gene, total_internal_neurons))
+                .collect(),
+            GeneFormat::Wide => connection_genes
+                .chunks_exact(2)
+                .map(|genes| gene_codec::decode_wide([genes[0], genes[1]], total_internal_neurons))
+                .collect()
+        };
 
         // Sorting the connections to ensure:
         // 1. Read SensoryNeuron
@@ -47,7 +126,7 @@ impl Brain {
         // SensoryToInternal => `sink` has no output
 
         // Map InternalNeuronID to its input/output count
-        let mut neurons_input_count: HashMap<InternalNeuronID, usize> = (0..MAX_INTERNAL_NEURONS)
+        let mut neurons_input_count: HashMap<InternalNeuronID, usize> = (0..total_internal_neurons)
             .into_iter().map(|num| (num, 0)).collect();
         let mut neurons_output_count = neurons_input_count.clone();
 
@@ -64,44 +143,35 @@ impl Brain {
             &mut neurons_output_count
         );
 
+        let activations = match config.activation_from_genome {
+            true => gene_codec::decode_activations(activation_genes, total_internal_neurons, config.activation),
+            false => vec![config.activation; total_internal_neurons]
+        };
+        let internal_neurons = activations.into_iter()
+            .map(|activation| InternalNeuron::with_activation(activation, config.leaky_relu_slope))
+            .collect();
+
+        Self::from_connections(connections, internal_neurons, config.neuron_decay)
+    }
+
+    // `connections` should already be sorted by ConnectionType
+    fn from_connections(connections: Vec<Connection>, internal_neurons: Vec<InternalNeuron>, neuron_decay: f64) -> Self {
         Brain {
+            plan: BrainPlan::compile(&connections),
             connections,
-            internal_neurons: vec![InternalNeuron::new(); MAX_INTERNAL_NEURONS],
+            internal_neurons,
+            neuron_decay,
         }
     }
 
-    pub fn neurons_empty_value_map(&self) -> (HashMap<SensoryNeuron, f64>, HashMap<ActionNeuron, f64>) {
-        let mut sensory_neuron_map = HashMap::new();
-        let mut action_neuron_map = HashMap::new();
-
-        for conn in &self.connections {
-            Self::gather_sensory_and_action_neurons(&conn, &mut sensory_neuron_map, &mut action_neuron_map);
-        }
-
-        (sensory_neuron_map, action_neuron_map)
+    // Sensors the brain reads from, sorted by ID
+    pub fn sensors(&self) -> &[SensoryNeuron] {
+        self.plan.sensors()
     }
 
-    fn gather_sensory_and_action_neurons(
-        conn: &Connection,
-        sensory_neuron_map: &mut HashMap<SensoryNeuron, f64>,
-        action_neuron_map: &mut HashMap<ActionNeuron, f64>
-    ) -> () {
-        match conn.connection_type() {
-            &ConnectionType::SensoryToInternal { source, .. } => {
-                sensory_neuron_map.insert(source, 0.0);
-            }
-
-            &ConnectionType::SensoryToAction { source, sink } => {
-                sensory_neuron_map.insert(source, 0.0);
-                action_neuron_map.insert(sink, 0.0);
-            }
-
-            &ConnectionType::InternalToAction { sink, .. } => {
-                action_neuron_map.insert(sink, 0.0);
-            }
-
-            _ => {}
-        }
+    // Actions the brain drives, sorted by ID
+    pub fn actions(&self) -> &[ActionNeuron] {
+        self.plan.actions()
     }
 
     fn recursive_brain_trimming(
@@ -182,7 +252,7 @@ impl Brain {
         neurons_output_count: &mut HashMap<InternalNeuronID, usize>
     ) -> () {
 
-        // Since the InternalNeuronID has been modulus to MAX_INTERNAL_NEURONS
+        // Since the InternalNeuronID has been modulus to the total internal neurons
         // we don't have to worry about non-existent key
         match conn.connection_type {
             ConnectionType::SensoryToInternal { sink, .. } => {
@@ -205,95 +275,34 @@ impl Brain {
         }
     }
 
-    pub fn process_connections(
-        &mut self,
-        sensory_neuron_map: &HashMap<SensoryNeuron, f64>,
-        action_neuron_map: &mut HashMap<ActionNeuron, f64>
-    ) -> () {
-        for conn in &self.connections {
-            Self::process_each_connection(conn, &mut self.internal_neurons, sensory_neuron_map, action_neuron_map);
-        }
+    // One step of the brain:
+    // 1. Action values start over from 0, they only hold this step's outputs
+    // 2. Internal neurons sum up every input, then activate all at once, right before
+    //    the InternalToAction connections read them
+    // InternalToInternal connections read the source's state from the previous step, so
+    // the order of the connections within a type doesn't matter
+    // Both slices are indexed by neuron ID; only `sensors()` need to be filled in
+    pub fn think(&mut self, sensor_values: &[f64], action_values: &mut [f64]) {
+        self.plan.run(&mut self.internal_neurons, self.neuron_decay, sensor_values, action_values);
     }
 
-    fn process_each_connection(
-        conn: &Connection,
-        internal_neurons: &mut Vec<InternalNeuron>,
-        sensory_neuron_map: &HashMap<SensoryNeuron, f64>,
-        action_neuron_map: &mut HashMap<ActionNeuron, f64>
-    ) -> () {
-        match conn.connection_type {
-            ConnectionType::SensoryToAction { source, sink } => {
-                let value = sensory_neuron_map[&source] * conn.weight;
-                *action_neuron_map.get_mut(&sink).unwrap() += value;
-            }
-
-            ConnectionType::SensoryToInternal { source, sink } => {
-                let value = sensory_neuron_map[&source] * conn.weight;
-                internal_neurons[sink].activation_func(value);
-            }
-
-            ConnectionType::InternalToInternal { source, sink } => {
-                let value = internal_neurons[source].state() * conn.weight;
-                internal_neurons[sink].activation_func(value);
-            }
-
-            ConnectionType::InternalToAction { source, sink } => {
-                let value = internal_neurons[source].state() * conn.weight;
-                *action_neuron_map.get_mut(&sink).unwrap() += value;
-            }
+    // Forget everything, as if the brain was just built
+    pub fn reset(&mut self) {
+        for neuron in &mut self.internal_neurons {
+            neuron.reset();
         }
     }
+
 }
 
+#[derive(Debug, PartialEq)]
 pub struct Connection {
     connection_type: ConnectionType,
     weight: f64,
 }
 
+// Genes <=> Connections are in `gene_codec`
 impl Connection {
-    pub fn from_gene(gene: Gene) -> Self {
-        // Gene bit layout (from front):
-        // bit 0-1 indicates ConnectionType
-        // bit 2-6 indicates source ID
-        // bit 7-11 indicates sink ID
-        // bit 12-15 indicates weight, a 4-bit signed integer
-        let conn_type_id = gene >> 14;
-        let source_id = ((gene >> 9) & 0b11_11_1) as usize;
-        let sink_id = ((gene >> 4) & 0b11_11_1) as usize;
-        let weight: f64 = ((gene & 0xF) as i8 - 8).into();
-
-        // This is kinda ugly, but it (might) work
-        // Btw, should never EVER fail!
-        let conn_type = match conn_type_id {
-            0 => ConnectionType::SensoryToAction {
-                source: SensoryNeuron::from_id(source_id % TOTAL_SENSORY_NEURON_VARIANT).unwrap(),
-                sink: ActionNeuron::from_id(sink_id % TOTAL_ACTION_NEURON_VARIANT).unwrap()
-            },
-
-            1 => ConnectionType::SensoryToInternal {
-                source: SensoryNeuron::from_id(source_id % TOTAL_SENSORY_NEURON_VARIANT).unwrap(),
-                sink: sink_id % MAX_INTERNAL_NEURONS
-            },
-
-            2 => ConnectionType::InternalToInternal {
-                source: source_id % MAX_INTERNAL_NEURONS,
-                sink: sink_id % MAX_INTERNAL_NEURONS
-            },
-
-            3 => ConnectionType::InternalToAction {
-                source: source_id % MAX_INTERNAL_NEURONS,
-                sink: ActionNeuron::from_id(sink_id % TOTAL_ACTION_NEURON_VARIANT).unwrap()
-            },
-
-            _ => panic!("WTF happened here!")
-        };
-
-        Self {
-            connection_type: conn_type,
-            weight
-        }
-    }
-
     pub fn connection_type(&self) -> &ConnectionType {
         &self.connection_type
     }
@@ -340,7 +349,12 @@ impl PartialOrd for ConnectionType {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::sensory_neuron::TOTAL_SENSORY_NEURON_VARIANT;
+    use super::action_neuron::TOTAL_ACTION_NEURON_VARIANT;
+    use crate::genome::Gene;
     use biosim_rust_macros::enum_from_id;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64;
 
     #[enum_from_id]
     #[derive(Debug, PartialEq)]
@@ -428,48 +442,280 @@ mod tests {
             },
         };
 
-        let mut brain = Brain {
-            connections: vec![connection1, connection2, connection3, connection4],
-            internal_neurons: vec![InternalNeuron::new(); 2]
-        };
+        let mut brain = Brain::from_connections(
+            vec![connection1, connection2, connection3, connection4],
+            vec![InternalNeuron::with_activation(Activation::LeakyRelu, DEFAULT_LEAKY_RELU_SLOPE); 2],
+            DEFAULT_NEURON_DECAY
+        );
+        assert_eq!(brain.sensors(), &[SensoryNeuron::Random]);
+        assert_eq!(brain.actions(), &[ActionNeuron::MoveNorth, ActionNeuron::MoveSouth]);
 
-        let mut sensory_neuron_map: HashMap<SensoryNeuron, f64> = HashMap::new();
-        let mut action_neuron_map: HashMap<ActionNeuron, f64> = HashMap::new();
+        let mut sensor_values = [0.0; TOTAL_SENSORY_NEURON_VARIANT];
+        let mut action_values = [0.0; TOTAL_ACTION_NEURON_VARIANT];
+        sensor_values[SensoryNeuron::Random as usize] = 0.5;
 
-        sensory_neuron_map.insert(SensoryNeuron::Random, 0.5);
-        action_neuron_map.insert(ActionNeuron::MoveNorth, 0.0);
-        action_neuron_map.insert(ActionNeuron::MoveSouth, 0.0);
+        // Neuron 0 only activates once every input is in, and neuron 1 reads
+        // neuron 0's state from the previous step
+        brain.think(&sensor_values, &mut action_values);
+        assert_eq!(action_values[ActionNeuron::MoveNorth as usize], 0.5);
+        assert_eq!(brain.internal_neurons[0].state(), -0.015);
+        assert_eq!(brain.internal_neurons[1].state(), 0.0);
+        assert_eq!(action_values[ActionNeuron::MoveSouth as usize], 0.0);
 
-        Brain::process_each_connection(
-            &brain.connections[0],
-            &mut brain.internal_neurons,
-            &sensory_neuron_map,
-            &mut action_neuron_map
-        );
-        assert_eq!(action_neuron_map[&ActionNeuron::MoveNorth], 0.5);
+        // Action values only hold the last step's outputs
+        brain.think(&sensor_values, &mut action_values);
+        assert_eq!(action_values[ActionNeuron::MoveNorth as usize], 0.5);
+        assert_eq!(brain.internal_neurons[0].state(), -0.015);
+        assert_eq!(brain.internal_neurons[1].state(), -0.00075);
+        assert_eq!(action_values[ActionNeuron::MoveSouth as usize], 0.0015);
 
-        Brain::process_each_connection(
-            &brain.connections[1],
-            &mut brain.internal_neurons,
-            &sensory_neuron_map,
-            &mut action_neuron_map
+        brain.reset();
+        assert!(brain.internal_neurons.iter().all(|neuron| neuron.state() == 0.0));
+    }
+
+    #[test]
+    fn recurrent_state_decays() {
+        // Neuron 0 only gets an input once, and keeps acting on it for a while
+        let mut brain = Brain::from_connections(
+            vec![
+                Connection {
+                    weight: 1.0,
+                    connection_type: ConnectionType::SensoryToInternal { source: SensoryNeuron::Random, sink: 0 }
+                },
+                Connection {
+                    weight: 1.0,
+                    connection_type: ConnectionType::InternalToAction { source: 0, sink: ActionNeuron::MoveEast }
+                }
+            ],
+            vec![InternalNeuron::with_activation(Activation::Identity, DEFAULT_LEAKY_RELU_SLOPE)],
+            0.5
         );
-        assert_eq!(brain.internal_neurons[0].state(), -0.015);
 
-        Brain::process_each_connection(
-            &brain.connections[2],
-            &mut brain.internal_neurons,
-            &sensory_neuron_map,
-            &mut action_neuron_map
+        let mut sensor_values = [0.0; TOTAL_SENSORY_NEURON_VARIANT];
+        let mut action_values = [0.0; TOTAL_ACTION_NEURON_VARIANT];
+        sensor_values[SensoryNeuron::Random as usize] = 1.0;
+        let mut outputs = vec![];
+        for _ in 0..4 {
+            brain.think(&sensor_values, &mut action_values);
+            outputs.push(action_values[ActionNeuron::MoveEast as usize]);
+            sensor_values[SensoryNeuron::Random as usize] = 0.0;
+        }
+
+        assert_eq!(outputs, vec![1.0, 0.5, 0.25, 0.125]);
+    }
+
+    #[test]
+    fn sum_every_input_before_activation() {
+        let sensory_to_internal = |weight: f64| Connection {
+            weight,
+            connection_type: ConnectionType::SensoryToInternal { source: SensoryNeuron::Random, sink: 0 }
+        };
+
+        let mut brain = Brain::from_connections(
+            vec![
+                sensory_to_internal(2.0),
+                sensory_to_internal(-3.0),
+                Connection {
+                    weight: 1.0,
+                    connection_type: ConnectionType::InternalToAction { source: 0, sink: ActionNeuron::MoveEast }
+                }
+            ],
+            vec![InternalNeuron::with_activation(Activation::Tanh, DEFAULT_LEAKY_RELU_SLOPE)],
+            DEFAULT_NEURON_DECAY
         );
-        assert_eq!(brain.internal_neurons[1].state(), -0.00075);
 
-        Brain::process_each_connection(
-            &brain.connections[3],
-            &mut brain.internal_neurons,
-            &sensory_neuron_map,
-            &mut action_neuron_map
+        let mut sensor_values = [0.0; TOTAL_SENSORY_NEURON_VARIANT];
+        let mut action_values = [0.0; TOTAL_ACTION_NEURON_VARIANT];
+        sensor_values[SensoryNeuron::Random as usize] = 0.5;
+        brain.think(&sensor_values, &mut action_values);
+
+        assert_eq!(brain.internal_neurons[0].state(), (-0.5_f64).tanh());
+        assert_eq!(action_values[ActionNeuron::MoveEast as usize], (-0.5_f64).tanh());
+    }
+
+    #[test]
+    fn activations_from_config() {
+        let genome = Genome::from_genes(vec![0x1234, 0x5678, 0x9abc, 0xdef0]);
+        let output = |neuron: &InternalNeuron| {
+            let mut neuron = *neuron;
+            neuron.accumulate(-0.7);
+            neuron.activate(DEFAULT_NEURON_DECAY);
+            neuron.state()
+        };
+
+        let brain = Brain::from_genome(&genome, BrainConfig { activation: Activation::Sigmoid, ..BrainConfig::default() });
+        assert!(brain.internal_neurons().iter().all(|neuron| output(neuron) == Activation::Sigmoid.apply(-0.7, 0.0)));
+
+        // 0x1234: neurons 0 to 3 get IDs 4, 6 (wrapped to 0), 0 and 1; that Gene is no connection anymore
+        let config = BrainConfig { activation_from_genome: true, ..BrainConfig::default() };
+        let brain = Brain::from_genome(&genome, config);
+        let activations = [Activation::Identity, Activation::Tanh, Activation::Tanh, Activation::Sigmoid];
+        for (neuron, activation) in brain.internal_neurons().iter().zip(activations) {
+            assert_eq!(output(neuron), activation.apply(-0.7, config.leaky_relu_slope));
+        }
+        assert!(brain.connections().len() <= 3);
+
+        // Connection genes don't affect activations
+        let mutated = Genome::from_genes(vec![0x1234, 0x0000, 0xffff, 0x0001]);
+        let brain = Brain::from_genome(&mutated, config);
+        for (neuron, activation) in brain.internal_neurons().iter().zip(activations) {
+            assert_eq!(output(neuron), activation.apply(-0.7, config.leaky_relu_slope));
+        }
+    }
+
+    #[test]
+    fn brain_from_genomes_of_any_length() {
+        for total_genes in [0, 1, 3, 8, 64] {
+            let bytes: Vec<u8> = (0..total_genes * 2).map(|i| (i * 37 % 256) as u8).collect();
+            let mut brain = Brain::from_genome(&Genome::from_byte_slice(&bytes), BrainConfig::default());
+            assert!(brain.connections().len() <= total_genes);
+
+            brain.think(&[0.0; TOTAL_SENSORY_NEURON_VARIANT], &mut [0.0; TOTAL_ACTION_NEURON_VARIANT]);
+        }
+    }
+
+    #[test]
+    fn brain_from_wide_genes() {
+        // 2 Genes per Connection, the odd one out is ignored
+        let genome = Genome::from_genes(vec![0x0001, 0x2000, 0x0002, 0x1000, 0xFFFF]);
+        let brain = Brain::from_genome(&genome, BrainConfig { gene_format: GeneFormat::Wide, ..BrainConfig::default() });
+        let weights: Vec<f64> = brain.connections().iter().map(|conn| conn.weight()).collect();
+        assert_eq!(weights, vec![1.0, 0.5]);
+    }
+
+    // The straightforward way of thinking: walk every connection, with values in HashMaps
+    // Kept around to check the compiled plan against, and to benchmark it
+    fn think_connection_by_connection(
+        brain: &mut Brain,
+        sensory_neuron_map: &HashMap<SensoryNeuron, f64>,
+        action_neuron_map: &mut HashMap<ActionNeuron, f64>
+    ) {
+        for value in action_neuron_map.values_mut() {
+            *value = 0.0;
+        }
+
+        let mut activated = false;
+        for conn in &brain.connections {
+            if !activated && matches!(conn.connection_type, ConnectionType::InternalToAction { .. }) {
+                brain.internal_neurons.iter_mut().for_each(|neuron| neuron.activate(brain.neuron_decay));
+                activated = true;
+            }
+
+            let internal_neurons = &mut brain.internal_neurons;
+            match conn.connection_type {
+                ConnectionType::SensoryToAction { source, sink } => {
+                    *action_neuron_map.get_mut(&sink).unwrap() += sensory_neuron_map[&source] * conn.weight;
+                }
+
+                ConnectionType::SensoryToInternal { source, sink } => {
+                    internal_neurons[sink].accumulate(sensory_neuron_map[&source] * conn.weight);
+                }
+
+                ConnectionType::InternalToInternal { source, sink } => {
+                    let value = internal_neurons[source].state() * conn.weight;
+                    internal_neurons[sink].accumulate(value);
+                }
+
+                ConnectionType::InternalToAction { source, sink } => {
+                    *action_neuron_map.get_mut(&sink).unwrap() += internal_neurons[source].state() * conn.weight;
+                }
+            }
+        }
+
+        if !activated {
+            brain.internal_neurons.iter_mut().for_each(|neuron| neuron.activate(brain.neuron_decay));
+        }
+    }
+
+    fn gen_brains(total: usize, seed: u64) -> Vec<(Brain, Brain)> {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let config = BrainConfig { activation_from_genome: true, neuron_decay: 0.3, ..BrainConfig::default() };
+
+        (0..total)
+            .map(|_| {
+                let genes: Vec<Gene> = (0..16).map(|_| rng.gen()).collect();
+                let genome = Genome::from_genes(genes);
+                (Brain::from_genome(&genome, config), Brain::from_genome(&genome, config))
+            })
+            .collect()
+    }
+
+    fn gen_sensor_values(rng: &mut Pcg64) -> [f64; TOTAL_SENSORY_NEURON_VARIANT] {
+        let mut sensor_values = [0.0; TOTAL_SENSORY_NEURON_VARIANT];
+        sensor_values.iter_mut().for_each(|value| *value = rng.gen_range(-1.0..=1.0));
+        sensor_values
+    }
+
+    #[test]
+    fn compiled_plan_matches_connections() {
+        let mut rng = Pcg64::seed_from_u64(1);
+        for (mut compiled, mut reference) in gen_brains(200, 0) {
+            let mut action_values = [0.0; TOTAL_ACTION_NEURON_VARIANT];
+            let mut action_neuron_map: HashMap<ActionNeuron, f64> = reference.actions()
+                .iter()
+                .map(|&neuron| (neuron, 0.0))
+                .collect();
+
+            for _ in 0..5 {
+                let sensor_values = gen_sensor_values(&mut rng);
+                let sensory_neuron_map: HashMap<SensoryNeuron, f64> = reference.sensors()
+                    .iter()
+                    .map(|&neuron| (neuron, sensor_values[neuron as usize]))
+                    .collect();
+
+                compiled.think(&sensor_values, &mut action_values);
+                think_connection_by_connection(&mut reference, &sensory_neuron_map, &mut action_neuron_map);
+
+                // Same additions in the same order, so the results are exactly the same
+                for (&neuron, &value) in &action_neuron_map {
+                    assert_eq!(action_values[neuron as usize], value);
+                }
+                for (a, b) in compiled.internal_neurons().iter().zip(reference.internal_neurons()) {
+                    assert_eq!(a.state(), b.state());
+                }
+            }
+        }
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_thinking
+    #[test]
+    #[ignore]
+    fn bench_thinking() {
+        use std::time::Instant;
+
+        const TOTAL_BRAINS: usize = 100_000;
+        const TOTAL_STEPS: usize = 20;
+
+        let (mut compiled, mut reference): (Vec<Brain>, Vec<Brain>) = gen_brains(TOTAL_BRAINS, 0).into_iter().unzip();
+        let sensor_values = gen_sensor_values(&mut Pcg64::seed_from_u64(1));
+
+        let mut action_neuron_maps: Vec<HashMap<ActionNeuron, f64>> = reference.iter()
+            .map(|brain| brain.actions().iter().map(|&neuron| (neuron, 0.0)).collect())
+            .collect();
+        let sensory_neuron_maps: Vec<HashMap<SensoryNeuron, f64>> = reference.iter()
+            .map(|brain| brain.sensors().iter().map(|&neuron| (neuron, sensor_values[neuron as usize])).collect())
+            .collect();
+        let start = Instant::now();
+        for _ in 0..TOTAL_STEPS {
+            for (brain, (sensory_neuron_map, action_neuron_map)) in reference.iter_mut().zip(sensory_neuron_maps.iter().zip(&mut action_neuron_maps)) {
+                think_connection_by_connection(brain, sensory_neuron_map, action_neuron_map);
+            }
+        }
+        let reference_time = start.elapsed();
+
+        let mut action_values = vec![[0.0; TOTAL_ACTION_NEURON_VARIANT]; TOTAL_BRAINS];
+        let start = Instant::now();
+        for _ in 0..TOTAL_STEPS {
+            for (brain, action_values) in compiled.iter_mut().zip(&mut action_values) {
+                brain.think(&sensor_values, action_values);
+            }
+        }
+        let compiled_time = start.elapsed();
+
+        println!(
+            "{} brains x {} steps: connection by connection {:?}, compiled {:?} ({:.1}x)",
+            TOTAL_BRAINS, TOTAL_STEPS, reference_time, compiled_time,
+            reference_time.as_secs_f64() / compiled_time.as_secs_f64()
         );
-        assert_eq!(action_neuron_map[&ActionNeuron::MoveSouth], 0.0015);
     }
 }
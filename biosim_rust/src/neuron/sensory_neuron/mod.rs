@@ -13,6 +13,11 @@ pub enum SensoryNeuron {
     DistToBarrierSouth,
     DistToBarrierEast,
     DistToBarrierWest,
+    Age,
+    Oscillator,
+    PopulationDensity,
+    BlockedForward,
+    LookForwardDistance,
+    LastMoveDirX,
+    LastMoveDirY,
 }
-
-
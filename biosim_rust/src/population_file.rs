@@ -0,0 +1,113 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::genome::{Genome, GenomeError};
+use crate::simulation::Simulation;
+use crate::vector2d::Vector2D;
+
+// Population file format, one creature per line:
+//
+// # Comments start with '#'
+// <hex genome> [<x> <y>]
+//
+// The genome uses `Genome::to_hex` format. Creatures without a position are placed randomly
+pub struct PopulationEntry {
+    pub genome: Genome,
+    pub position: Option<Vector2D<usize>>
+}
+
+#[derive(Debug, Error)]
+pub enum PopulationFileError {
+    #[error("Couldn't read/write population file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {0}: {1}")]
+    InvalidGenome(usize, GenomeError),
+    #[error("Line {0}: expected `<hex genome> [<x> <y>]`")]
+    MalformedLine(usize),
+    #[error("Population has {0} creatures, but the field only has {1} cells")]
+    TooManyCreatures(usize, usize),
+    #[error("Creature #{0} is placed outside the field, or on an occupied cell ({1}, {2})")]
+    InvalidPosition(usize, usize, usize),
+}
+
+pub fn parse_population(text: &str) -> Result<Vec<PopulationEntry>, PopulationFileError> {
+    let mut population = vec![];
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = raw_line.split('#').next().unwrap().trim();
+        if line.is_empty() { continue }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let genome = Genome::from_hex(fields[0])
+            .map_err(|err| PopulationFileError::InvalidGenome(line_number, err))?;
+
+        let position = match fields[1..] {
+            [] => None,
+            [x, y] => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => Some(Vector2D::new(x, y)),
+                _ => return Err(PopulationFileError::MalformedLine(line_number))
+            },
+            _ => return Err(PopulationFileError::MalformedLine(line_number))
+        };
+
+        population.push(PopulationEntry { genome, position });
+    }
+
+    Ok(population)
+}
+
+pub fn load_population<P: AsRef<Path>>(path: P) -> Result<Vec<PopulationEntry>, PopulationFileError> {
+    parse_population(&fs::read_to_string(path)?)
+}
+
+pub fn write_population<W: Write>(writer: &mut W, sim: &Simulation) -> std::io::Result<()> {
+    writeln!(writer, "# Generation #{}, step #{}", sim.generation(), sim.current_step())?;
+
+    for creature in sim.creatures() {
+        let pos = creature.position();
+        writeln!(writer, "{} {} {}", creature.genome().to_hex(), pos.x, pos.y)?;
+    }
+
+    Ok(())
+}
+
+pub fn save_population<P: AsRef<Path>>(path: P, sim: &Simulation) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_population(&mut writer, sim)?;
+    writer.flush()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_population_file() {
+        let text = "
+            # Two creatures worth sharing
+            64225ad2 3 4
+            00ff00ff   # no position
+        ";
+
+        let population = parse_population(text).unwrap();
+        assert_eq!(population.len(), 2);
+        assert_eq!(population[0].genome, Genome::from_byte_slice(&[0x64, 0x22, 0x5a, 0xd2]));
+        assert_eq!(population[0].position, Some(Vector2D::new(3, 4)));
+        assert_eq!(population[1].genome.genes(), &vec![0xff00, 0xff00]);
+        assert_eq!(population[1].position, None);
+    }
+
+    #[test]
+    fn population_file_errors() {
+        let error_message = |text: &str| parse_population(text).err().unwrap().to_string();
+
+        assert_eq!(error_message("64225ad2\n64225"), "Line 2: Hex Genome should have 4 digits per Gene, got 5 digits");
+        assert_eq!(error_message("64225ad2 3"), "Line 1: expected `<hex genome> [<x> <y>]`");
+        assert_eq!(error_message("64225ad2 3 -4"), "Line 1: expected `<hex genome> [<x> <y>]`");
+    }
+}
@@ -30,11 +30,7 @@ impl Color {
     }
 
     pub fn from_xrgb_u32(num: u32) -> Self {
-        Self (
-            (0xFF & (num >> 16)) as u8,
-            (0xFF & (num >> 8)) as u8,
-            (0xFF & num) as u8
-        )
+        Self::new(0xFF & (num >> 16), 0xFF & (num >> 8), 0xFF & num)
     }
 }
 
@@ -44,7 +40,9 @@ struct RendererAttributes {
     pub field_height: usize,
 
     pub field_color: Color,
-    pub border_color: Color
+    pub border_color: Color,
+    // Falls back to `border_color`
+    pub barrier_color: Option<Color>
 }
 
 #[derive(Debug, Error)]
@@ -118,6 +116,11 @@ impl Renderer {
 
     pub fn render(&self, sim: &Simulation) -> Result<Buffer, RendererError> {
         let mut buffer = self.empty_field_buffer.clone();
+        let barrier_color = self.attr.barrier_color.unwrap_or(self.attr.border_color);
+        for pos in sim.grid().barriers() {
+            self.plot_pixel(&mut buffer, pos.x+1, pos.y+1, barrier_color)?;
+        }
+
         for c in sim.creatures().iter() {
             let pos = c.position();
             // Simulation aren't aware that field coordinates is smaller than the whole buffer
@@ -169,6 +172,11 @@ impl RendererBuilder {
         self
     }
 
+    pub fn with_barrier_color(mut self, color: Color) -> Self {
+        self.attr.barrier_color = Some(color);
+        self
+    }
+
     pub fn with_field_dimensions(mut self, width: usize, height: usize) -> Self {
         self.attr.field_width = width;
         self.attr.field_height = height;
@@ -179,6 +187,9 @@ impl RendererBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::barrier::Barrier;
+    use crate::simulation::SimulationConfigBuilder;
+    use crate::vector2d::Vector2D;
 
     #[test]
     fn new_invalid_color() {
@@ -186,4 +197,38 @@ mod tests {
 
         assert_eq!(color, Color::new(0, 34, 0));
     }
+
+    #[test]
+    fn render_barriers() {
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(4, 3)
+            .with_total_genes(2)
+            .with_barrier(Barrier::Line { start: Vector2D::new(1, 0), end: Vector2D::new(1, 2) })
+            .build()
+            .unwrap();
+        let sim = Simulation::new(config);
+
+        let field_color = Color::new(1, 1, 1);
+        let border_color = Color::new(2, 2, 2);
+        let barrier_color = Color::new(3, 3, 3);
+        let render = |renderer: RendererBuilder| {
+            let mut renderer = renderer
+                .with_field_dimensions(4, 3)
+                .with_field_color(field_color)
+                .with_border_color(border_color)
+                .build()
+                .unwrap();
+            renderer.init().unwrap();
+            renderer.render(&sim).unwrap()
+        };
+
+        // Buffer is 6x5, with the field starting at (1, 1)
+        let buffer = render(RendererBuilder::new().with_barrier_color(barrier_color));
+        assert_eq!(buffer[2 + 6], barrier_color);
+        assert_eq!(buffer[2 + 3 * 6], barrier_color);
+        assert_eq!(buffer[3 + 6], field_color);
+
+        let buffer = render(RendererBuilder::new());
+        assert_eq!(buffer[2 + 2 * 6], border_color);
+    }
 }
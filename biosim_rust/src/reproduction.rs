@@ -0,0 +1,148 @@
+use rand::Rng;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+
+use crate::genome::{Crossover, Genome};
+use crate::simulation::SimulationConfig;
+use crate::vector2d::Vector2D;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reproduction {
+    // Offspring is a (mutated) copy of a single parent
+    Asexual,
+    // Offspring is a (mutated) crossover of 2 parents
+    Sexual { crossover: Crossover, pairing: Pairing }
+}
+
+// How the second parent is picked for sexual reproduction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pairing {
+    // Any other survivor, with equal chance
+    Random,
+    // The closest other survivor on the field
+    NearestNeighbour,
+    // Any other survivor, more likely the more similar their Genome is
+    GeneticSimilarity,
+}
+
+// A creature that made it through selection
+pub struct Survivor {
+    pub genome: Genome,
+    pub position: Vector2D<usize>
+}
+
+impl Pairing {
+    // Pick the index of `parent`'s mate among `survivors`
+    // A lone survivor has no choice but to mate with itself
+    // `config` is only needed for distances, which depend on the field's Topology
+    pub fn pick_mate<R: Rng>(&self, parent: usize, survivors: &[Survivor], config: &SimulationConfig, rng: &mut R) -> usize {
+        if survivors.len() < 2 {
+            return parent;
+        }
+
+        match self {
+            Pairing::Random => {
+                // Skip over the parent itself
+                let mate = rng.gen_range(0..survivors.len() - 1);
+                if mate >= parent { mate + 1 } else { mate }
+            }
+
+            Pairing::NearestNeighbour => {
+                let origin = survivors[parent].position;
+                let distance_squared = |pos: &Vector2D<usize>| {
+                    config.topology.distance_squared(&origin, pos, config.field_width, config.field_height)
+                };
+
+                // Ties are broken by the lowest index, so it's deterministic
+                (0..survivors.len())
+                    .filter(|&i| i != parent)
+                    .min_by_key(|&i| distance_squared(&survivors[i].position))
+                    .unwrap()
+            }
+
+            Pairing::GeneticSimilarity => {
+                // Small base weight, so completely different survivors can still be picked
+                let weights = survivors.iter()
+                    .enumerate()
+                    .map(|(i, survivor)| match i == parent {
+                        true => 0.0,
+                        false => survivor.genome.similarity(&survivors[parent].genome) + f64::EPSILON
+                    });
+
+                WeightedIndex::new(weights).unwrap().sample(rng)
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    use super::*;
+    use crate::simulation::SimulationConfigBuilder;
+    use crate::topology::Topology;
+
+    fn gen_config(topology: Topology) -> SimulationConfig {
+        SimulationConfigBuilder::new()
+            .with_field_dimensions(10, 10)
+            .with_total_genes(4)
+            .with_topology(topology)
+            .build()
+            .unwrap()
+    }
+
+    fn survivor(gene: u16, x: usize, y: usize) -> Survivor {
+        Survivor {
+            genome: Genome::from_byte_slice(&gene.to_le_bytes().repeat(4)),
+            position: Vector2D::new(x, y)
+        }
+    }
+
+    #[test]
+    fn pairing_never_picks_itself() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        let config = gen_config(Topology::Walled);
+        let survivors = vec![survivor(0, 0, 0), survivor(1, 5, 5), survivor(2, 9, 9)];
+
+        for pairing in [Pairing::Random, Pairing::NearestNeighbour, Pairing::GeneticSimilarity] {
+            for parent in 0..survivors.len() {
+                for _ in 0..20 {
+                    assert_ne!(pairing.pick_mate(parent, &survivors, &config, &mut rng), parent);
+                }
+            }
+        }
+
+        assert_eq!(Pairing::Random.pick_mate(0, &survivors[..1], &config, &mut rng), 0);
+    }
+
+    #[test]
+    fn pairing_nearest_neighbour() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        let config = gen_config(Topology::Walled);
+        let survivors = vec![survivor(0, 0, 0), survivor(0, 9, 9), survivor(0, 2, 1)];
+
+        assert_eq!(Pairing::NearestNeighbour.pick_mate(0, &survivors, &config, &mut rng), 2);
+        assert_eq!(Pairing::NearestNeighbour.pick_mate(1, &survivors, &config, &mut rng), 2);
+
+        // Across the edges, (0, 0) and (9, 9) are neighbours
+        let config = gen_config(Topology::Toroidal);
+        assert_eq!(Pairing::NearestNeighbour.pick_mate(0, &survivors, &config, &mut rng), 1);
+    }
+
+    #[test]
+    fn pairing_genetic_similarity() {
+        let mut rng = Pcg64::seed_from_u64(0);
+        let config = gen_config(Topology::Walled);
+        // Survivor #1 is identical to #0, while #2 is its exact opposite
+        let survivors = vec![survivor(0, 0, 0), survivor(0, 1, 1), survivor(0xFFFF, 2, 2)];
+
+        let picked_similar = (0..1000)
+            .filter(|_| Pairing::GeneticSimilarity.pick_mate(0, &survivors, &config, &mut rng) == 1)
+            .count();
+        assert_eq!(picked_similar, 1000);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+
+use crate::creature::Creature;
+use crate::simulation::Simulation;
+use crate::vector2d::Vector2D;
+
+
+// Decides which creatures get to breed at the end of a generation
+// Any `Fn(&Creature, &Simulation) -> bool` closure works as a custom criterion
+pub trait SelectionCriterion: Send + Sync {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool;
+
+    fn and<C: SelectionCriterion>(self, other: C) -> And<Self, C>
+        where Self: Sized {
+        And(self, other)
+    }
+
+    fn or<C: SelectionCriterion>(self, other: C) -> Or<Self, C>
+        where Self: Sized {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+        where Self: Sized {
+        Not(self)
+    }
+}
+
+impl<F> SelectionCriterion for F
+    where F: Fn(&Creature, &Simulation) -> bool + Send + Sync {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        self(creature, sim)
+    }
+}
+
+impl SelectionCriterion for Box<dyn SelectionCriterion> {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        self.as_ref().is_survivor(creature, sim)
+    }
+}
+
+
+// No selection pressure at all
+#[derive(Debug, Clone, Copy)]
+pub struct Everyone;
+
+impl SelectionCriterion for Everyone {
+    fn is_survivor(&self, _: &Creature, _: &Simulation) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West
+}
+
+// Survive in one half of the field
+// For odd dimensions, the middle row/column is not part of either half
+#[derive(Debug, Clone, Copy)]
+pub struct HalfField(pub Side);
+
+impl SelectionCriterion for HalfField {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        let pos = creature.position();
+        let (width, height) = (sim.field_width(), sim.field_height());
+
+        match self.0 {
+            Side::North => pos.y < height / 2,
+            Side::South => pos.y >= height - height / 2,
+            Side::West => pos.x < width / 2,
+            Side::East => pos.x >= width - width / 2,
+        }
+    }
+}
+
+// Survive within `radius` cells (euclidean distance) of `center`
+// On a torus, the circle wraps around the edges
+#[derive(Debug, Clone, Copy)]
+pub struct Circle {
+    pub center: Vector2D<usize>,
+    pub radius: f64
+}
+
+impl SelectionCriterion for Circle {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        sim.distance_squared(creature.position(), &self.center) as f64 <= self.radius * self.radius
+    }
+}
+
+// Survive in the outermost `distance` rows/columns of the field
+#[derive(Debug, Clone, Copy)]
+pub struct NearWall {
+    pub distance: usize
+}
+
+impl SelectionCriterion for NearWall {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        let pos = creature.position();
+
+        pos.x < self.distance
+            || pos.y < self.distance
+            || pos.x + self.distance >= sim.field_width()
+            || pos.y + self.distance >= sim.field_height()
+    }
+}
+
+// Survive in any of the 4 `size` x `size` squares at the corners of the field
+#[derive(Debug, Clone, Copy)]
+pub struct Corners {
+    pub size: usize
+}
+
+impl SelectionCriterion for Corners {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        let pos = creature.position();
+        let near_west_or_east = pos.x < self.size || pos.x + self.size >= sim.field_width();
+        let near_north_or_south = pos.y < self.size || pos.y + self.size >= sim.field_height();
+
+        near_west_or_east && near_north_or_south
+    }
+}
+
+// Survive on any of the given cells, e.g. the safe zone of an ArenaMap
+#[derive(Debug, Clone)]
+pub struct Zone(pub HashSet<Vector2D<usize>>);
+
+impl SelectionCriterion for Zone {
+    fn is_survivor(&self, creature: &Creature, _: &Simulation) -> bool {
+        self.0.contains(creature.position())
+    }
+}
+
+
+// Combinators
+pub struct And<A, B>(pub A, pub B);
+pub struct Or<A, B>(pub A, pub B);
+pub struct Not<A>(pub A);
+
+impl<A, B> SelectionCriterion for And<A, B>
+    where A: SelectionCriterion, B: SelectionCriterion {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        self.0.is_survivor(creature, sim) && self.1.is_survivor(creature, sim)
+    }
+}
+
+impl<A, B> SelectionCriterion for Or<A, B>
+    where A: SelectionCriterion, B: SelectionCriterion {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        self.0.is_survivor(creature, sim) || self.1.is_survivor(creature, sim)
+    }
+}
+
+impl<A> SelectionCriterion for Not<A>
+    where A: SelectionCriterion {
+    fn is_survivor(&self, creature: &Creature, sim: &Simulation) -> bool {
+        !self.0.is_survivor(creature, sim)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::creature::CreatureRng;
+    use crate::genome::Genome;
+    use crate::neuron::BrainConfig;
+    use crate::simulation::SimulationConfigBuilder;
+
+    fn gen_simulation() -> Simulation {
+        let config = SimulationConfigBuilder::new()
+            .with_field_dimensions(10, 10)
+            .with_total_genes(4)
+            .build()
+            .unwrap();
+
+        Simulation::new(config)
+    }
+
+    fn creature_at(x: usize, y: usize) -> Creature {
+        Creature::new(
+            Vector2D::new(x, y),
+            Genome::from_byte_slice(&[0; 8]),
+            CreatureRng::from_seed([0; 32]),
+            BrainConfig::default()
+        ).unwrap()
+    }
+
+    #[test]
+    fn half_field_selection() {
+        let sim = gen_simulation();
+
+        assert!(HalfField(Side::East).is_survivor(&creature_at(5, 0), &sim));
+        assert!(!HalfField(Side::East).is_survivor(&creature_at(4, 0), &sim));
+        assert!(HalfField(Side::West).is_survivor(&creature_at(4, 9), &sim));
+        assert!(HalfField(Side::North).is_survivor(&creature_at(9, 4), &sim));
+        assert!(HalfField(Side::South).is_survivor(&creature_at(0, 5), &sim));
+        assert!(!HalfField(Side::South).is_survivor(&creature_at(0, 4), &sim));
+    }
+
+    #[test]
+    fn circle_selection() {
+        let sim = gen_simulation();
+        let circle = Circle { center: Vector2D::new(5, 5), radius: 2.0 };
+
+        assert!(circle.is_survivor(&creature_at(5, 5), &sim));
+        assert!(circle.is_survivor(&creature_at(7, 5), &sim));
+        assert!(!circle.is_survivor(&creature_at(7, 7), &sim));
+    }
+
+    #[test]
+    fn wall_and_corner_selection() {
+        let sim = gen_simulation();
+        let near_wall = NearWall { distance: 2 };
+        let corners = Corners { size: 2 };
+
+        assert!(near_wall.is_survivor(&creature_at(1, 5), &sim));
+        assert!(near_wall.is_survivor(&creature_at(5, 8), &sim));
+        assert!(!near_wall.is_survivor(&creature_at(2, 7), &sim));
+
+        assert!(corners.is_survivor(&creature_at(0, 9), &sim));
+        assert!(corners.is_survivor(&creature_at(8, 1), &sim));
+        assert!(!corners.is_survivor(&creature_at(1, 5), &sim));
+    }
+
+    #[test]
+    fn zone_selection() {
+        let sim = gen_simulation();
+        let zone = Zone(HashSet::from([Vector2D::new(2, 3), Vector2D::new(7, 7)]));
+
+        assert!(zone.is_survivor(&creature_at(2, 3), &sim));
+        assert!(zone.is_survivor(&creature_at(7, 7), &sim));
+        assert!(!zone.is_survivor(&creature_at(3, 2), &sim));
+    }
+
+    #[test]
+    fn combine_selection_criteria() {
+        let sim = gen_simulation();
+        let east_not_near_wall = HalfField(Side::East).and(NearWall { distance: 1 }.not());
+        let custom = |creature: &Creature, _: &Simulation| creature.position().y == 3;
+        let west_or_custom = HalfField(Side::West).or(custom);
+
+        assert!(east_not_near_wall.is_survivor(&creature_at(6, 5), &sim));
+        assert!(!east_not_near_wall.is_survivor(&creature_at(9, 5), &sim));
+        assert!(!east_not_near_wall.is_survivor(&creature_at(2, 5), &sim));
+
+        assert!(west_or_custom.is_survivor(&creature_at(1, 1), &sim));
+        assert!(west_or_custom.is_survivor(&creature_at(8, 3), &sim));
+        assert!(!west_or_custom.is_survivor(&creature_at(8, 4), &sim));
+    }
+}
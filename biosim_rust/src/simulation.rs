@@ -1,160 +1,827 @@
-use std::cell::{RefCell, Ref};
-use std::collections::HashMap;
 use std::error::Error;
-use rand::{SeedableRng, RngCore};
-use rand::seq::SliceRandom;
-use rand_pcg::Pcg64;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use thiserror::Error;
+use rand::{SeedableRng, RngCore, Rng};
+use rand::seq::{SliceRandom, index};
+use rayon::prelude::*;
 
-use crate::creature::{Creature, CreatureRng};
-use crate::genome::Genome;
-use crate::vector2d::Vector2D;
+use crate::barrier::Barrier;
+use crate::checkpoint::{self, CheckpointError, TrackedPcg64};
+use crate::creature::{Creature, CreatureRng, CreatureID};
+use crate::grid::OccupancyGrid;
+use crate::map_file::ArenaMap;
+use crate::genome::{Genome, PointMutation, LengthMutation};
+use crate::neuron::{BrainConfig, GeneFormat};
+use crate::neuron::internal_neuron::Activation;
+use crate::population_file::{PopulationEntry, PopulationFileError};
+use crate::reproduction::{Reproduction, Survivor};
+use crate::selection::{SelectionCriterion, Everyone, Zone};
+use crate::topology::Topology;
+use crate::vector2d::{Direction, Vector2D};
 
 pub type RngSeed = [u8; 32];
 
+const DEFAULT_STEPS_PER_GENERATION: usize = 300;
+const DEFAULT_POINT_MUTATION: PointMutation = PointMutation::PerGene(0.001);
+
+// What happens when more than one creature wants to move into the same cell in one step
+// Every policy only uses Simulation's own Rng, so it stays deterministic for a given RngSeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementConflict {
+    // Move requests are processed in a shuffled order; the first one to claim a cell gets it
+    FirstCome,
+    // One of the creatures that want the same cell is picked at random
+    RandomWinner,
+    // Nobody moves into a contested cell
+    AllBlocked,
+}
+
 pub struct Simulation {
-    field_width: usize,
-    field_height: usize,
-    occupancy_map: HashMap<Vector2D<usize>, bool>,
-    all_field_position: Vec<Vector2D<usize>>,
+    config: SimulationConfig,
+    grid: OccupancyGrid,
 
-    initial_total_creature: usize,
-    total_genes: usize,
+    current_step: usize,
+    generation: usize,
 
-    creatures: RefCell<Vec<Creature>>,
-    rng: Pcg64
+    creatures: Vec<Creature>,
+    rng: TrackedPcg64
 }
 
 impl Simulation {
-    pub fn new(field_width: usize, field_height: usize,
-        initial_total_creature: usize, seed: RngSeed,
-        total_genes: usize) -> Self {
-
-        let mut occupancy_map = HashMap::new();
-        let mut all_field_position = vec![];
-        let mut pos: Vector2D<usize>;
-        for x in 0..field_width {
-            for y in 0..field_height {
-                pos = Vector2D::new(x, y);
-                all_field_position.push(pos);
-                occupancy_map.insert(pos, false);
-            }
-        }
+    pub fn new(config: SimulationConfig) -> Self {
+        let mut sim = Self {
+            grid: OccupancyGrid::new(config.field_width, config.field_height),
+            creatures: vec![],
+            current_step: 0,
+            generation: 0,
+            rng: TrackedPcg64::from_seed(config.seed),
+            config
+        };
 
-        Self {
-            field_width, field_height,
-            occupancy_map,
-            all_field_position,
-            creatures: RefCell::new(vec![]),
-            initial_total_creature,
-            total_genes,
-            rng: Pcg64::from_seed(seed)
+        // Barriers are placed once, and stay for the whole run
+        for barrier in &sim.config.barriers {
+            barrier.place(&mut sim.grid, &mut sim.rng);
         }
+
+        sim
     }
 
     pub fn init(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut all_possible_coords = self.all_field_position
-            .choose_multiple(&mut self.rng, self.initial_total_creature);
+        self.spawn_random_population()
+    }
 
-        let mut new_creature: Creature;
+    // Like `init`, but the first generation comes from a population file
+    // Creatures without a position are placed on random free cells
+    pub fn init_from_population(&mut self, population: Vec<PopulationEntry>) -> Result<(), Box<dyn Error>> {
+        let total_cells = self.config.field_width * self.config.field_height - self.grid.total_barriers();
+        if population.len() > total_cells {
+            return Err(PopulationFileError::TooManyCreatures(population.len(), total_cells).into());
+        }
+
+        // Claim the preset positions first, so random placement can avoid them
+        for (i, entry) in population.iter().enumerate() {
+            if let Some(pos) = entry.position {
+                if self.grid.is_occupied(&pos) != Some(false) {
+                    self.grid.clear();
+                    return Err(PopulationFileError::InvalidPosition(i, pos.x, pos.y).into());
+                }
+                self.grid.set(&pos, Some(i));
+            }
+        }
+
+        let total_unplaced = population.iter().filter(|entry| entry.position.is_none()).count();
+        let mut random_positions = self.random_spawn_positions(total_unplaced)?.into_iter();
+
+        let (genomes, positions) = population.into_iter()
+            .map(|entry| {
+                let pos = entry.position.unwrap_or_else(|| random_positions.next().unwrap());
+                (entry.genome, pos)
+            })
+            .unzip();
+
+        self.spawn_population_at(genomes, positions, false)
+    }
+
+    // Every cell without a creature or a barrier, in row-major order
+    fn free_cells(&self) -> Vec<Vector2D<usize>> {
+        (0..self.config.field_width * self.config.field_height)
+            .map(|i| self.grid.index_to_position(i))
+            .filter(|pos| self.grid.is_occupied(pos) == Some(false))
+            .collect()
+    }
+
+    // Fill the field with creatures made of random genes
+    fn spawn_random_population(&mut self) -> Result<(), Box<dyn Error>> {
         // Gene is u16, so you need 2 u8 for each Gene
-        let mut genome_byte_array = vec![0_u8; self.total_genes * 2];
+        let mut genome_byte_array = vec![0_u8; self.config.total_genes * 2];
+        let mut parents = vec![];
+
+        for _ in 0..self.config.initial_total_creature {
+            self.rng.fill_bytes(&mut genome_byte_array);
+            parents.push(Genome::from_byte_slice(&genome_byte_array));
+        }
+
+        self.spawn_population(parents, false)
+    }
+
+    // Place one creature per Genome at random, unoccupied positions
+    // Creatures are expected to be cleared beforehand, as their index is used as CreatureID
+    // If `mutate` is set, each Genome is replicated (and mutated) using the new creature's
+    // own CreatureRng, so offspring are reproducible from RngSeed
+    fn spawn_population(&mut self, genomes: Vec<Genome>, mutate: bool) -> Result<(), Box<dyn Error>> {
+        let positions = self.random_spawn_positions(genomes.len())?;
+        self.spawn_population_at(genomes, positions, mutate)
+    }
+
+    // `total` distinct free cells, picked at random
+    // With spawn regions, they are split evenly between regions (the first regions get the leftovers)
+    fn random_spawn_positions(&mut self, total: usize) -> Result<Vec<Vector2D<usize>>, SimulationConfigError> {
+        let areas = match self.config.spawn_regions.is_empty() {
+            true => vec![self.free_cells()],
+            false => self.config.spawn_regions.iter()
+                .map(|region| region.iter()
+                    .filter(|pos| self.grid.is_occupied(pos) == Some(false))
+                    .copied()
+                    .collect::<Vec<_>>())
+                .collect()
+        };
+
+        let mut positions = Vec::with_capacity(total);
+        for (i, area) in areas.iter().enumerate() {
+            let area_total = total / areas.len() + usize::from(i < total % areas.len());
+            if area_total > area.len() {
+                return Err(match self.config.spawn_regions.is_empty() {
+                    true => SimulationConfigError::PopulationExceedsField(area_total, area.len()),
+                    false => SimulationConfigError::SpawnRegionFull(i, area_total, area.len())
+                });
+            }
+
+            positions.extend(
+                index::sample(&mut self.rng, area.len(), area_total)
+                    .into_iter()
+                    .map(|j| area[j])
+            );
+        }
+
+        Ok(positions)
+    }
 
+    // Same as `spawn_population`, but with a known position for every Genome
+    fn spawn_population_at(&mut self, genomes: Vec<Genome>, positions: Vec<Vector2D<usize>>, mutate: bool) -> Result<(), Box<dyn Error>> {
+        let mut new_creature: Creature;
         let current_gen_seed = self.rng.next_u64();
         let mut creature_rng;
-        let mut current_position: Vector2D<usize>;
 
-        for i in 0..self.initial_total_creature {
+        for (i, (genome, position)) in genomes.into_iter().zip(positions).enumerate() {
             creature_rng = CreatureRng::seed_from_u64(current_gen_seed);
             creature_rng.set_stream(i as u64);
-            self.rng.fill_bytes(&mut genome_byte_array);
 
-            current_position = *all_possible_coords.next().unwrap();
-            self.occupancy_map.insert(current_position, true);
+            let genome = match mutate {
+                true => {
+                    let mut genome = genome.replicate(&mut creature_rng, self.config.point_mutation);
+                    if let Some(length_mutation) = self.config.length_mutation {
+                        genome.mutate_length(&mut creature_rng, length_mutation);
+                    }
+                    genome
+                }
+                false => genome
+            };
+
+            self.grid.set(&position, Some(i));
 
-            new_creature = Creature::new(
-                current_position,
-                Genome::from_byte_slice(&genome_byte_array),
-                creature_rng
-            )?;
+            // Newborns face a random direction, until their first move
+            let heading = Direction::random(&mut creature_rng);
+            new_creature = Creature::new(position, genome, creature_rng, self.config.brain)?;
+            new_creature.set_last_move_direction(heading);
 
-            self.creatures.borrow_mut().push(new_creature);
+            self.creatures.push(new_creature);
         }
 
+        debug_assert!(
+            self.grid.total_occupied() == self.creatures.len()
+                && self.creatures.iter().enumerate().all(|(id, creature)| self.creature_at(creature.position()) == Some(id)),
+            "Grid out of sync with the creatures"
+        );
+
         Ok(())
     }
 
+    // Run every step of the current generation, then breed the next one
+    // Returns the number of survivors of the finished generation
+    pub fn run_generation(&mut self) -> Result<usize, Box<dyn Error>> {
+        while self.current_step < self.config.steps_per_generation {
+            self.step();
+        }
+
+        self.next_generation()
+    }
+
+    // Apply the selection criterion, then repopulate the field with the survivors' offspring
+    // If nobody survives, the field is repopulated with random creatures instead
+    // Returns the number of survivors
+    pub fn next_generation(&mut self) -> Result<usize, Box<dyn Error>> {
+        let survivors: Vec<Survivor> = self.creatures()
+            .iter()
+            .filter(|creature| self.config.selection.is_survivor(creature, self))
+            .map(|creature| Survivor {
+                genome: creature.genome().clone(),
+                position: *creature.position()
+            })
+            .collect();
+        let total_survivors = survivors.len();
+
+        self.creatures.clear();
+        self.grid.clear();
+
+        self.generation += 1;
+        self.current_step = 0;
+
+        if survivors.is_empty() {
+            self.spawn_random_population()?;
+            return Ok(total_survivors);
+        }
+
+        // Each survivor gets (almost) the same amount of offspring; the leftover slots are
+        // given to randomly picked survivors
+        let mut offspring = vec![];
+        for i in 0..self.config.initial_total_creature {
+            let parent = match i / total_survivors {
+                0 => i,
+                _ => self.rng.gen_range(0..total_survivors)
+            };
+
+            let genome = match self.config.reproduction {
+                Reproduction::Asexual => survivors[parent].genome.clone(),
+                Reproduction::Sexual { crossover, pairing } => {
+                    let mate = pairing.pick_mate(parent, &survivors, &self.config, &mut self.rng);
+                    survivors[parent].genome.crossover(&survivors[mate].genome, &mut self.rng, crossover)
+                }
+            };
+            offspring.push(genome);
+        }
+
+        // Shuffle, so the creature ordering doesn't leak the parent's ordering
+        offspring.shuffle(&mut self.rng);
+        self.spawn_population(offspring, true)?;
+
+        Ok(total_survivors)
+    }
+
     pub fn step(&mut self) -> () {
-        let mut all_signals = vec![];
-        for creature in self.creatures.borrow_mut().iter_mut() {
-            creature.gather_sensory_data(self);
-            creature.think();
+        // Creatures are mutated in parallel while sharing the rest of the simulation, read-only,
+        // through a SensorContext (borrowing other fields than `creatures`)
+        // Each creature only touches its own CreatureRng, so the thread count doesn't matter
+        let context = SensorContext::new(&self.config, &self.grid, self.current_step);
+        let new_generation = self.current_step == 0;
+        let all_signals: Vec<(CreatureID, Signal)> = self.creatures
+            .par_iter_mut()
+            .enumerate()
+            .flat_map_iter(|(id, creature)| {
+                if new_generation {
+                    creature.reset_brain();
+                }
 
-            let creature_signals = creature.execute_actions(self);
-            all_signals.push(creature_signals);
-        };
+                creature.gather_sensory_data(&context);
+                creature.think();
+
+                creature.execute_actions(&context)
+                    .into_iter()
+                    .map(move |signal| (id, signal))
+            })
+            .collect();
 
         // The above loop uses immutable ref. to self, while processing signals requires a mutable
-        // access to self. We process it later, after all creatures have completed thinking.
-        for creature_signals in all_signals {
-            self.process_signals(creature_signals);
-        }
+        // access to self. We process it later (in CreatureID order), after all creatures have
+        // completed thinking.
+        self.process_signals(all_signals);
+
+        self.current_step += 1;
     }
 
-    fn process_signals(&mut self, signals: Vec<Signal>) {
-        for signal in signals {
-            println!("{:?}", signal);
+    fn process_signals(&mut self, signals: Vec<(CreatureID, Signal)>) {
+        let mut move_requests = vec![];
+        for (id, signal) in signals {
             match signal {
-                Signal::PositionChanged { old, new } => self.update_occupancy_map(old, new)
+                Signal::MoveRequested { new } => move_requests.push((id, new))
+            }
+        }
+
+        self.resolve_movement(move_requests);
+    }
+
+    // Every request was made against the occupancy at the start of the step, so several
+    // creatures might be heading to the same cell
+    fn resolve_movement(&mut self, mut move_requests: Vec<(CreatureID, Vector2D<usize>)>) {
+        match self.config.movement_conflict {
+            MovementConflict::FirstCome => move_requests.shuffle(&mut self.rng),
+
+            MovementConflict::RandomWinner | MovementConflict::AllBlocked => {
+                // Group requests by target cell; sorting keeps it independent of creature order
+                move_requests.sort_by_key(|&(id, target)| (target.y, target.x, id));
+
+                let mut winners = vec![];
+                for contenders in move_requests.chunk_by(|a, b| a.1 == b.1) {
+                    match (self.config.movement_conflict, contenders.len()) {
+                        (_, 1) => winners.push(contenders[0]),
+                        (MovementConflict::RandomWinner, _) => winners.push(*contenders.choose(&mut self.rng).unwrap()),
+                        _ => {}
+                    }
+                }
+
+                move_requests = winners;
+            }
+        }
+
+        for (id, target) in move_requests {
+            if self.grid.is_occupied(&target) != Some(false) {
+                continue;
+            }
+
+            let creature = &mut self.creatures[id];
+            self.grid.move_occupant(creature.position(), &target);
+            creature.set_position(target);
+            creature.set_last_move_blocked(false);
+        }
+    }
+
+    // Layout: header, field dimensions, generation & step counters, Rng state, then every creature
+    // Selection criterion and the rest of SimulationConfig are not saved; pass the same config
+    // when loading to continue the run exactly where it stopped
+    pub fn save_checkpoint<W: Write>(&self, writer: &mut W) -> Result<(), CheckpointError> {
+        checkpoint::write_header(writer)?;
+
+        checkpoint::write_usize(writer, self.config.field_width)?;
+        checkpoint::write_usize(writer, self.config.field_height)?;
+        checkpoint::write_usize(writer, self.generation)?;
+        checkpoint::write_usize(writer, self.current_step)?;
+
+        writer.write_all(&self.rng.seed())?;
+        checkpoint::write_u128(writer, self.rng.total_draws())?;
+
+        checkpoint::write_usize(writer, self.creatures.len())?;
+        for creature in &self.creatures {
+            creature.write_checkpoint(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load_checkpoint<R: Read>(config: SimulationConfig, reader: &mut R) -> Result<Self, CheckpointError> {
+        checkpoint::read_header(reader)?;
+
+        let field_width = checkpoint::read_usize(reader)?;
+        let field_height = checkpoint::read_usize(reader)?;
+        if (field_width, field_height) != (config.field_width, config.field_height) {
+            return Err(CheckpointError::FieldMismatch(field_width, field_height, config.field_width, config.field_height));
+        }
+
+        let mut sim = Simulation::new(config);
+        sim.generation = checkpoint::read_usize(reader)?;
+        sim.current_step = checkpoint::read_usize(reader)?;
+
+        let seed = checkpoint::read_bytes(reader)?;
+        sim.rng = TrackedPcg64::from_state(seed, checkpoint::read_u128(reader)?);
+
+        let total_creatures = checkpoint::read_usize(reader)?;
+        for id in 0..total_creatures {
+            let creature = Creature::read_checkpoint(reader, sim.config.brain)?;
+            if sim.grid.is_occupied(creature.position()) != Some(false) {
+                return Err(CheckpointError::InvalidPosition(id));
             }
+
+            sim.grid.set(creature.position(), Some(id));
+            sim.creatures.push(creature);
         }
+
+        Ok(sim)
+    }
+
+    pub fn save_checkpoint_file<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.save_checkpoint(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load_checkpoint_file<P: AsRef<Path>>(config: SimulationConfig, path: P) -> Result<Self, CheckpointError> {
+        Self::load_checkpoint(config, &mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn creatures(&self) -> &Vec<Creature> {
+        &self.creatures
+    }
+
+    pub fn field_width(&self) -> usize {
+        self.config.field_width
+    }
+
+    pub fn field_height(&self) -> usize {
+        self.config.field_height
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn current_step(&self) -> usize {
+        self.current_step
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
     }
 
-    fn update_occupancy_map(&mut self, old: Vector2D<usize>, new: Vector2D<usize>) {
-        self.occupancy_map.insert(old, false);
-        self.occupancy_map.insert(new, true);
+    // Which creature (index into `creatures()`) sits on `pos`, if any
+    pub fn creature_at(&self, pos: &Vector2D<usize>) -> Option<CreatureID> {
+        self.grid.occupant(pos)
     }
 
-    pub fn creatures(&self) -> Ref<Vec<Creature>> {
-        self.creatures.borrow()
+    pub fn distance_squared(&self, a: &Vector2D<usize>, b: &Vector2D<usize>) -> usize {
+        self.config.topology.distance_squared(a, b, self.config.field_width, self.config.field_height)
+    }
+
+    pub fn grid(&self) -> &OccupancyGrid {
+        &self.grid
+    }
+}
+
+// Read-only view of a Simulation for creatures while they sense & act: the field, the
+// config and the clock, but not the other creatures, which are being mutated at the same time
+// Creatures find each other through the grid (`creature_at`)
+#[derive(Clone, Copy)]
+pub struct SensorContext<'a> {
+    config: &'a SimulationConfig,
+    grid: &'a OccupancyGrid,
+    current_step: usize,
+}
+
+impl<'a> SensorContext<'a> {
+    pub fn new(config: &'a SimulationConfig, grid: &'a OccupancyGrid, current_step: usize) -> Self {
+        Self { config, grid, current_step }
     }
 
     pub fn field_width(&self) -> usize {
-        self.field_width
+        self.config.field_width
     }
 
     pub fn field_height(&self) -> usize {
-        self.field_height
+        self.config.field_height
+    }
+
+    pub fn current_step(&self) -> usize {
+        self.current_step
+    }
+
+    pub fn steps_per_generation(&self) -> usize {
+        self.config.steps_per_generation
     }
 
     pub fn is_position_occupied(&self, pos: &Vector2D<usize>) -> Option<bool> {
-        if pos.x >= self.field_width || pos.y >= self.field_height {
-            return None;
+        self.grid.is_occupied(pos)
+    }
+
+    // Which creature (index into `creatures()`) sits on `pos`, if any
+    pub fn creature_at(&self, pos: &Vector2D<usize>) -> Option<CreatureID> {
+        self.grid.occupant(pos)
+    }
+
+    // The neighbouring cell of `pos` toward `direction` (see `Topology::step`)
+    pub fn step_position(&self, pos: &Vector2D<usize>, direction: Direction) -> Option<(Vector2D<usize>, Direction)> {
+        self.config.topology.step(pos, direction, self.config.field_width, self.config.field_height)
+    }
+
+    // Move `pos` by `offset` (see `Topology::translate`)
+    pub fn translate_position(&self, pos: &Vector2D<usize>, offset: Vector2D<isize>) -> Option<Vector2D<usize>> {
+        self.config.topology.translate(pos, offset, self.config.field_width, self.config.field_height)
+    }
+
+    // Ray-march from `from` (excluded) toward `direction`, counting empty cells until hitting the
+    // field border or a creature. Gives up after `max_distance` cells
+    // The ray follows the topology, so it wraps around or bounces off the edges
+    pub fn free_cells_along(&self, from: &Vector2D<usize>, mut direction: Direction, max_distance: usize) -> usize {
+        let mut current = *from;
+        for distance in 0..max_distance {
+            match self.step_position(&current, direction) {
+                Some((next, next_direction)) if self.grid.is_occupied(&next) == Some(false) => {
+                    current = next;
+                    direction = next_direction;
+                }
+                _ => return distance
+            }
+        }
+
+        max_distance
+    }
+
+    // Like `free_cells_along`, but only barriers (and walls) block the way, creatures are ignored
+    // Reflecting edges count as walls
+    pub fn cells_until_barrier(&self, from: &Vector2D<usize>, direction: Direction, max_distance: usize) -> usize {
+        let mut current = *from;
+        for distance in 0..max_distance {
+            match self.translate_position(&current, direction.offset()) {
+                Some(next) if !self.grid.is_barrier(&next) => current = next,
+                _ => return distance
+            }
         }
 
-        Some(self.occupancy_map.get(pos).is_some_and(|x| *x))
+        max_distance
     }
 }
 
 #[derive(Debug)]
 pub enum Signal {
-    PositionChanged { old: Vector2D<usize>, new: Vector2D<usize> }
+    MoveRequested { new: Vector2D<usize> }
+}
+
+pub struct SimulationConfig {
+    pub field_width: usize,
+    pub field_height: usize,
+    pub initial_total_creature: usize,
+    pub seed: RngSeed,
+    pub total_genes: usize,
+
+    pub steps_per_generation: usize,
+    pub point_mutation: PointMutation,
+    pub length_mutation: Option<LengthMutation>,
+    pub reproduction: Reproduction,
+    pub selection: Box<dyn SelectionCriterion>,
+    pub movement_conflict: MovementConflict,
+    pub topology: Topology,
+    pub barriers: Vec<Barrier>,
+    // If any, creatures only spawn on these cells (see `random_spawn_positions`)
+    pub spawn_regions: Vec<Vec<Vector2D<usize>>>,
+
+    pub brain: BrainConfig
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            field_width: 0,
+            field_height: 0,
+            initial_total_creature: 0,
+            seed: [0; 32],
+            total_genes: 0,
+            steps_per_generation: DEFAULT_STEPS_PER_GENERATION,
+            point_mutation: DEFAULT_POINT_MUTATION,
+            length_mutation: None,
+            reproduction: Reproduction::Asexual,
+            selection: Box::new(Everyone),
+            movement_conflict: MovementConflict::FirstCome,
+            topology: Topology::Walled,
+            barriers: vec![],
+            spawn_regions: vec![],
+            brain: BrainConfig::default()
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SimulationConfigError {
+    #[error("Total width/height of Field should be bigger than 0_usize ({0}, {1})")]
+    FieldTooSmall(usize, usize),
+    #[error("Initial population ({0}) doesn't fit in the field area ({1})")]
+    PopulationExceedsField(usize, usize),
+    #[error("Genome should have at least 1 Gene")]
+    NoGenes,
+    #[error("Generation should last at least 1 step")]
+    NoSteps,
+    #[error("Mutation rate should be between 0.0 and 1.0 ({0})")]
+    InvalidMutationRate(f64),
+    #[error("Genome length bounds should satisfy 1 <= min ({0}) <= total genes ({1}) <= max ({2})")]
+    InvalidGenomeLength(usize, usize, usize),
+    #[error("Brain should have at least 1 internal neuron")]
+    NoInternalNeurons,
+    #[error("{0} internal neurons can't all be addressed by genes, which allow at most {1}")]
+    TooManyInternalNeurons(usize, usize),
+    #[error("Neuron decay should be between 0.0 and 1.0 ({0})")]
+    InvalidNeuronDecay(f64),
+    #[error("Spawn region #{0} only has {2} free cells for {1} creatures")]
+    SpawnRegionFull(usize, usize, usize),
+}
+
+pub struct SimulationConfigBuilder {
+    config: SimulationConfig
+}
+
+impl SimulationConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: SimulationConfig::default()
+        }
+    }
+
+    pub fn build(self) -> Result<SimulationConfig, SimulationConfigError> {
+        let config = self.config;
+        let field_area = config.field_width * config.field_height;
+
+        if field_area == 0 {
+            return Err(SimulationConfigError::FieldTooSmall(config.field_width, config.field_height));
+        }
+
+        if config.initial_total_creature > field_area {
+            return Err(SimulationConfigError::PopulationExceedsField(config.initial_total_creature, field_area));
+        }
+
+        if config.total_genes == 0 {
+            return Err(SimulationConfigError::NoGenes);
+        }
+
+        if config.steps_per_generation == 0 {
+            return Err(SimulationConfigError::NoSteps);
+        }
+
+        let mut rates = vec![match config.point_mutation {
+            PointMutation::PerBit(rate) | PointMutation::PerGene(rate) => rate
+        }];
+
+        if let Some(length_mutation) = config.length_mutation {
+            let (min, max) = (length_mutation.min_genes, length_mutation.max_genes);
+            if min == 0 || min > config.total_genes || config.total_genes > max {
+                return Err(SimulationConfigError::InvalidGenomeLength(min, config.total_genes, max));
+            }
+
+            rates.push(length_mutation.insertion_rate);
+            rates.push(length_mutation.deletion_rate);
+        }
+
+        if let Some(&rate) = rates.iter().find(|rate| !(0.0..=1.0).contains(*rate)) {
+            return Err(SimulationConfigError::InvalidMutationRate(rate));
+        }
+
+        if config.brain.total_internal_neurons == 0 {
+            return Err(SimulationConfigError::NoInternalNeurons);
+        }
+
+        let max_internal_neurons = config.brain.gene_format.max_internal_neurons();
+        if config.brain.total_internal_neurons > max_internal_neurons {
+            return Err(SimulationConfigError::TooManyInternalNeurons(config.brain.total_internal_neurons, max_internal_neurons));
+        }
+
+        if !(0.0..=1.0).contains(&config.brain.neuron_decay) {
+            return Err(SimulationConfigError::InvalidNeuronDecay(config.brain.neuron_decay));
+        }
+
+        Ok(config)
+    }
+
+    pub fn with_field_dimensions(mut self, width: usize, height: usize) -> Self {
+        self.config.field_width = width;
+        self.config.field_height = height;
+        self
+    }
+
+    pub fn with_population(mut self, total_creature: usize) -> Self {
+        self.config.initial_total_creature = total_creature;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: RngSeed) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn with_total_genes(mut self, total_genes: usize) -> Self {
+        self.config.total_genes = total_genes;
+        self
+    }
+
+    pub fn with_steps_per_generation(mut self, steps: usize) -> Self {
+        self.config.steps_per_generation = steps;
+        self
+    }
+
+    pub fn with_point_mutation(mut self, mutation: PointMutation) -> Self {
+        self.config.point_mutation = mutation;
+        self
+    }
+
+    pub fn with_length_mutation(mut self, mutation: LengthMutation) -> Self {
+        self.config.length_mutation = Some(mutation);
+        self
+    }
+
+    pub fn with_reproduction(mut self, reproduction: Reproduction) -> Self {
+        self.config.reproduction = reproduction;
+        self
+    }
+
+    pub fn with_selection<C: SelectionCriterion + 'static>(mut self, selection: C) -> Self {
+        self.config.selection = Box::new(selection);
+        self
+    }
+
+    pub fn with_movement_conflict(mut self, movement_conflict: MovementConflict) -> Self {
+        self.config.movement_conflict = movement_conflict;
+        self
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.config.topology = topology;
+        self
+    }
+
+    // Can be called several times; creatures are split evenly between regions
+    pub fn with_spawn_region(mut self, cells: Vec<Vector2D<usize>>) -> Self {
+        self.config.spawn_regions.push(cells);
+        self
+    }
+
+    // Takes field dimensions, barriers and spawn regions from the map
+    // If the map has a safe zone, it replaces the selection criterion
+    pub fn with_arena_map(mut self, map: ArenaMap) -> Self {
+        self = self.with_field_dimensions(map.width, map.height);
+        if !map.barriers.is_empty() {
+            self = self.with_barrier(Barrier::Cells(map.barriers));
+        }
+
+        for region in map.spawn_regions.into_values() {
+            self = self.with_spawn_region(region);
+        }
+
+        if !map.safe_zone.is_empty() {
+            self = self.with_selection(Zone(map.safe_zone));
+        }
+
+        self
+    }
+
+    // Can be called several times; barriers are placed in order
+    pub fn with_barrier(mut self, barrier: Barrier) -> Self {
+        self.config.barriers.push(barrier);
+        self
+    }
+
+    pub fn with_internal_neurons(mut self, total_internal_neurons: usize) -> Self {
+        self.config.brain.total_internal_neurons = total_internal_neurons;
+        self
+    }
+
+    pub fn with_leaky_relu_slope(mut self, slope: f64) -> Self {
+        self.config.brain.leaky_relu_slope = slope;
+        self
+    }
+
+    // Same activation for every internal neuron
+    pub fn with_activation(mut self, activation: Activation) -> Self {
+        self.config.brain.activation = activation;
+        self.config.brain.activation_from_genome = false;
+        self
+    }
+
+    // Below 1.0, internal neurons keep part of their state from one step to the next
+    pub fn with_neuron_decay(mut self, decay: f64) -> Self {
+        self.config.brain.neuron_decay = decay;
+        self
+    }
+
+    // With GeneFormat::Wide, each Connection takes 2 of the `total_genes`
+    pub fn with_gene_format(mut self, gene_format: GeneFormat) -> Self {
+        self.config.brain.gene_format = gene_format;
+        self
+    }
+
+    // Each internal neuron's activation is encoded in the genome instead
+    pub fn with_activation_from_genome(mut self) -> Self {
+        self.config.brain.activation_from_genome = true;
+        self
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::selection::{HalfField, Side};
+    use crate::genome::Crossover;
+    use crate::reproduction::Pairing;
+    use std::collections::HashSet;
+
+    fn gen_config_builder(width: usize, height: usize, total_creature: usize, seed: RngSeed, total_genes: usize) -> SimulationConfigBuilder {
+        SimulationConfigBuilder::new()
+            .with_field_dimensions(width, height)
+            .with_population(total_creature)
+            .with_seed(seed)
+            .with_total_genes(total_genes)
+    }
+
+    fn context(sim: &Simulation) -> SensorContext<'_> {
+        SensorContext::new(&sim.config, &sim.grid, sim.current_step)
+    }
 
     #[test]
     fn get_field_width() {
-        let sim = Simulation::new(100,100,20,[0; 32], 4);
-        assert_eq!(sim.field_width, 100);
+        let sim = Simulation::new(gen_config_builder(100, 100, 20, [0; 32], 4).build().unwrap());
+        assert_eq!(sim.field_width(), 100);
     }
 
     #[test]
     fn get_field_height() {
-        let sim = Simulation::new(100,100,20,[0; 32], 4);
-        assert_eq!(sim.field_height, 100);
+        let sim = Simulation::new(gen_config_builder(100, 100, 20, [0; 32], 4).build().unwrap());
+        assert_eq!(sim.field_height(), 100);
     }
 
     #[test]
@@ -162,16 +829,477 @@ mod tests {
         let creature = Creature::new(
             Vector2D::new(100, 100),
             Genome::from_byte_slice(&[0; 10]),
-            CreatureRng::from_entropy()
+            CreatureRng::from_entropy(),
+            BrainConfig::default()
         ).unwrap();
 
-        let mut sim = Simulation::new(200, 200, 1, [0;32], 4);
-        sim.creatures.borrow_mut().push(creature);
-        sim.occupancy_map.insert(Vector2D::new(100, 100), true);
+        let mut sim = Simulation::new(gen_config_builder(200, 200, 1, [0;32], 4).build().unwrap());
+        sim.creatures.push(creature);
+        sim.grid.set(&Vector2D::new(100, 100), Some(0));
+
+        println!("{:?}", sim.creatures[0].position());
+
+        assert_eq!(context(&sim).is_position_occupied(&Vector2D::new(100, 100)), Some(true));
+        assert_eq!(context(&sim).is_position_occupied(&Vector2D::new(10, 10)), Some(false));
+        assert_eq!(context(&sim).is_position_occupied(&Vector2D::new(200, 10)), None);
+        assert_eq!(sim.creature_at(&Vector2D::new(100, 100)), Some(0));
+        assert_eq!(sim.creature_at(&Vector2D::new(10, 10)), None);
+    }
+
+    #[test]
+    fn run_generations() {
+        let mut sim = Simulation::new(gen_config_builder(30, 30, 50, [3; 32], 8)
+            .with_steps_per_generation(10)
+            .with_selection(HalfField(Side::West))
+            .build().unwrap());
+        sim.init().unwrap();
+
+        for generation in 1..=3 {
+            sim.run_generation().unwrap();
+
+            assert_eq!(sim.generation(), generation);
+            assert_eq!(sim.current_step(), 0);
+            assert_eq!(sim.creatures().len(), 50);
+
+            assert_eq!(sim.grid.total_occupied(), 50);
+            for (id, creature) in sim.creatures().iter().enumerate() {
+                assert_eq!(sim.creature_at(creature.position()), Some(id));
+            }
+        }
+    }
+
+    #[test]
+    fn run_generations_with_sexual_reproduction() {
+        let mut sim = Simulation::new(gen_config_builder(30, 30, 40, [8; 32], 8)
+            .with_steps_per_generation(5)
+            .with_selection(HalfField(Side::East))
+            .with_reproduction(Reproduction::Sexual {
+                crossover: Crossover::TwoPoint,
+                pairing: Pairing::NearestNeighbour
+            })
+            .build().unwrap());
+        sim.init().unwrap();
+
+        for _ in 0..3 {
+            sim.run_generation().unwrap();
+            assert_eq!(sim.creatures().len(), 40);
+            assert!(sim.creatures().iter().all(|creature| creature.genome().genes().len() == 8));
+        }
+    }
+
+    #[test]
+    fn run_generations_with_length_mutation() {
+        let mut sim = Simulation::new(gen_config_builder(30, 30, 40, [13; 32], 8)
+            .with_steps_per_generation(2)
+            .with_length_mutation(LengthMutation {
+                insertion_rate: 0.3,
+                deletion_rate: 0.3,
+                min_genes: 2,
+                max_genes: 16
+            })
+            .with_reproduction(Reproduction::Sexual {
+                crossover: Crossover::SinglePoint,
+                pairing: Pairing::Random
+            })
+            .build().unwrap());
+        sim.init().unwrap();
+
+        for _ in 0..10 {
+            sim.run_generation().unwrap();
+            assert_eq!(sim.creatures().len(), 40);
+            assert!(sim.creatures().iter().all(|creature| (2..=16).contains(&creature.genome().genes().len())));
+        }
+
+        let lengths: HashSet<usize> = sim.creatures().iter().map(|creature| creature.genome().genes().len()).collect();
+        assert!(lengths.len() > 1);
+    }
+
+    #[test]
+    fn repopulate_without_survivors() {
+        let mut sim = Simulation::new(gen_config_builder(20, 20, 30, [5; 32], 4)
+            .with_selection(Everyone.not())
+            .build().unwrap());
+        sim.init().unwrap();
+
+        assert_eq!(sim.next_generation().unwrap(), 0);
+        assert_eq!(sim.creatures().len(), 30);
+    }
+
+    fn assert_no_shared_cell(sim: &Simulation) {
+        let positions: HashSet<Vector2D<usize>> = sim.creatures().iter().map(|creature| *creature.position()).collect();
+        assert_eq!(positions.len(), sim.creatures().len());
+        assert_eq!(sim.grid.total_occupied(), sim.creatures().len());
+
+        for (id, creature) in sim.creatures().iter().enumerate() {
+            assert_eq!(sim.creature_at(creature.position()), Some(id));
+        }
+    }
+
+    #[test]
+    fn init_from_population_file() {
+        use crate::population_file::{self, parse_population};
+
+        // 3x3 field filled up completely, 2 creatures have a preset position
+        let text = "
+            64225ad2 0 0
+            00ff00ff
+            00ff00ff 2 1
+            a1b2c3d4
+            a1b2c3d4
+            a1b2c3d4
+            a1b2c3d4
+            a1b2c3d4
+            a1b2c3d4
+        ";
+        let mut sim = Simulation::new(gen_config_builder(3, 3, 9, [8; 32], 2).build().unwrap());
+        sim.init_from_population(parse_population(text).unwrap()).unwrap();
+
+        assert_no_shared_cell(&sim);
+        assert_eq!(sim.creatures()[0].position(), &Vector2D::new(0, 0));
+        assert_eq!(sim.creatures()[2].position(), &Vector2D::new(2, 1));
+        assert_eq!(sim.creatures()[1].genome().to_hex(), "00ff00ff");
+
+        // Exported population loads back the same
+        let mut bytes = vec![];
+        population_file::write_population(&mut bytes, &sim).unwrap();
+        let mut reloaded = Simulation::new(gen_config_builder(3, 3, 9, [8; 32], 2).build().unwrap());
+        reloaded.init_from_population(parse_population(&String::from_utf8(bytes).unwrap()).unwrap()).unwrap();
+        for (a, b) in sim.creatures().iter().zip(reloaded.creatures()) {
+            assert_eq!(a.position(), b.position());
+            assert_eq!(a.genome(), b.genome());
+        }
+
+        let mut sim = Simulation::new(gen_config_builder(3, 3, 9, [8; 32], 2).build().unwrap());
+        let err = sim.init_from_population(parse_population("64225ad2 1 1\n00ff00ff 1 1").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Creature #1 is placed outside the field, or on an occupied cell (1, 1)");
+        assert!(sim.init_from_population(parse_population("64225ad2 3 0").unwrap()).is_err());
+        assert!(sim.init_from_population(parse_population(&"64225ad2\n".repeat(10)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn ray_march_until_obstacle() {
+        use crate::population_file::parse_population;
+
+        let mut sim = Simulation::new(gen_config_builder(10, 10, 2, [0; 32], 1).build().unwrap());
+        sim.init_from_population(parse_population("0000 2 2\n0000 6 2").unwrap()).unwrap();
+        let from = Vector2D::new(2, 2);
+
+        assert_eq!(context(&sim).free_cells_along(&from, Direction::East, 20), 3);
+        assert_eq!(context(&sim).free_cells_along(&from, Direction::North, 20), 2);
+        assert_eq!(context(&sim).free_cells_along(&from, Direction::SouthEast, 20), 7);
+        assert_eq!(context(&sim).free_cells_along(&from, Direction::South, 5), 5);
+        assert_eq!(context(&sim).free_cells_along(&Vector2D::new(0, 0), Direction::NorthWest, 5), 0);
+    }
+
+    #[test]
+    fn ray_march_across_edges() {
+        use crate::population_file::parse_population;
+
+        let gen_simulation = |topology: Topology| {
+            let mut sim = Simulation::new(gen_config_builder(10, 10, 2, [0; 32], 1)
+                .with_topology(topology)
+                .build().unwrap());
+            sim.init_from_population(parse_population("0000 1 5\n0000 7 2").unwrap()).unwrap();
+            sim
+        };
+        let from = Vector2D::new(1, 2);
+
+        assert_eq!(context(&gen_simulation(Topology::Walled)).free_cells_along(&from, Direction::West, 20), 1);
+        // Wraps around to (9, 2), and stops right after (8, 2)
+        assert_eq!(context(&gen_simulation(Topology::Toroidal)).free_cells_along(&from, Direction::West, 20), 3);
+        // Bounces off the west edge, then runs into (7, 2)
+        assert_eq!(context(&gen_simulation(Topology::Reflecting)).free_cells_along(&from, Direction::West, 20), 7);
+    }
+
+    #[test]
+    fn creatures_stay_on_any_topology() {
+        for topology in [Topology::Walled, Topology::Toroidal, Topology::Reflecting] {
+            let mut sim = Simulation::new(gen_config_builder(8, 8, 30, [13; 32], 16)
+                .with_topology(topology)
+                .build().unwrap());
+            sim.init().unwrap();
+
+            for _ in 0..50 {
+                sim.step();
+                assert_no_shared_cell(&sim);
+                assert!(sim.creatures().iter().all(|creature| sim.grid().is_in_bounds(creature.position())));
+            }
+        }
+    }
+
+    #[test]
+    fn barriers_block_creatures() {
+        let gen_builder = |total_creature: usize| gen_config_builder(10, 10, total_creature, [3; 32], 16)
+            .with_barrier(Barrier::Rectangle { corner: Vector2D::new(0, 0), width: 10, height: 3 })
+            .with_barrier(Barrier::RandomScatter { total_cells: 10 });
+
+        let mut sim = Simulation::new(gen_builder(40).build().unwrap());
+        sim.init().unwrap();
+        assert_eq!(sim.grid().total_barriers(), 40);
+
+        for _ in 0..50 {
+            sim.step();
+            assert_no_shared_cell(&sim);
+            assert!(sim.creatures().iter().all(|creature| !sim.grid().is_barrier(creature.position())));
+        }
+
+        // Only 60 cells are left for creatures
+        let mut sim = Simulation::new(gen_builder(61).build().unwrap());
+        assert_eq!(sim.init().unwrap_err().to_string(), "Initial population (61) doesn't fit in the field area (60)");
+    }
+
+    #[test]
+    fn spawn_in_arena_map_regions() {
+        use crate::map_file::parse_map;
+
+        let map = "\
+#######
+#000.1#
+#00#.1#
+#SS#..#
+#######";
+        let gen_builder = |total_creature: usize| gen_config_builder(1, 1, total_creature, [5; 32], 4)
+            .with_arena_map(parse_map(map).unwrap());
+
+        let mut sim = Simulation::new(gen_builder(5).build().unwrap());
+        assert_eq!((sim.field_width(), sim.field_height()), (7, 5));
+        assert_eq!(sim.grid().total_barriers(), 7 * 2 + 3 * 2 + 2);
+
+        // 3 creatures in region 0, 2 in region 1, every generation
+        sim.init().unwrap();
+        for _ in 0..3 {
+            assert_no_shared_cell(&sim);
+            let in_region_1 = sim.creatures().iter().filter(|creature| creature.position().x == 5).count();
+            assert_eq!(in_region_1, 2);
+            assert!(sim.creatures().iter().all(|creature| creature.position().x <= 3 || creature.position().x == 5));
+            sim.next_generation().unwrap();
+        }
+
+        // Region 1 only has 2 cells
+        let mut sim = Simulation::new(gen_builder(6).build().unwrap());
+        assert_eq!(sim.init().unwrap_err().to_string(), "Spawn region #1 only has 2 free cells for 3 creatures");
+    }
+
+    #[test]
+    fn distance_to_barriers() {
+        let sim = Simulation::new(gen_config_builder(10, 10, 0, [0; 32], 1)
+            .with_barrier(Barrier::Line { start: Vector2D::new(0, 6), end: Vector2D::new(9, 6) })
+            .build().unwrap());
+        let from = Vector2D::new(4, 2);
+
+        assert_eq!(context(&sim).cells_until_barrier(&from, Direction::South, 10), 3);
+        assert_eq!(context(&sim).cells_until_barrier(&from, Direction::North, 10), 2);
+        assert_eq!(context(&sim).cells_until_barrier(&from, Direction::East, 3), 3);
+        assert_eq!(context(&sim).free_cells_along(&from, Direction::South, 10), 3);
+    }
+
+    #[test]
+    fn creatures_never_share_a_cell() {
+        for policy in [MovementConflict::FirstCome, MovementConflict::RandomWinner, MovementConflict::AllBlocked] {
+            // Crowded field, so conflicts are bound to happen
+            let mut sim = Simulation::new(gen_config_builder(12, 12, 100, [21; 32], 16)
+                .with_movement_conflict(policy)
+                .build().unwrap());
+            sim.init().unwrap();
+
+            for _ in 0..50 {
+                sim.step();
+                assert_no_shared_cell(&sim);
+            }
+        }
+    }
+
+    fn gen_contested_simulation(policy: MovementConflict, seed: RngSeed) -> Simulation {
+        let mut sim = Simulation::new(gen_config_builder(10, 10, 0, seed, 4)
+            .with_movement_conflict(policy)
+            .build().unwrap());
+
+        for (id, pos) in [Vector2D::new(4, 5), Vector2D::new(6, 5), Vector2D::new(0, 0)].into_iter().enumerate() {
+            let creature = Creature::new(pos, Genome::from_byte_slice(&[0; 8]), CreatureRng::seed_from_u64(0), BrainConfig::default()).unwrap();
+            sim.creatures.push(creature);
+            sim.grid.set(&pos, Some(id));
+        }
+
+        sim
+    }
+
+    #[test]
+    fn resolve_movement_conflicts() {
+        // Creature #0 and #1 both want (5, 5), #2 is unopposed
+        let requests = vec![(0, Vector2D::new(5, 5)), (1, Vector2D::new(5, 5)), (2, Vector2D::new(1, 0))];
+
+        let mut sim = gen_contested_simulation(MovementConflict::AllBlocked, [0; 32]);
+        sim.resolve_movement(requests.clone());
+        assert_eq!(sim.creature_at(&Vector2D::new(5, 5)), None);
+        assert_eq!(sim.creature_at(&Vector2D::new(1, 0)), Some(2));
+        assert_no_shared_cell(&sim);
+
+        for policy in [MovementConflict::FirstCome, MovementConflict::RandomWinner] {
+            let mut winners = HashSet::new();
+            for seed in 0..20 {
+                let mut sim = gen_contested_simulation(policy, [seed; 32]);
+                sim.resolve_movement(requests.clone());
+
+                let winner = sim.creature_at(&Vector2D::new(5, 5)).unwrap();
+                assert_eq!(sim.creature_at(&Vector2D::new(1, 0)), Some(2));
+                assert_no_shared_cell(&sim);
+                winners.insert(winner);
+
+                // Same seed, same winner
+                let mut sim = gen_contested_simulation(policy, [seed; 32]);
+                sim.resolve_movement(requests.clone());
+                assert_eq!(sim.creature_at(&Vector2D::new(5, 5)), Some(winner));
+            }
+
+            assert_eq!(winners.len(), 2);
+        }
+    }
+
+    #[test]
+    fn same_result_regardless_of_thread_count() {
+        let run_with_threads = |total_threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(total_threads).build().unwrap();
+            pool.install(|| {
+                let mut sim = Simulation::new(gen_config_builder(40, 40, 300, [17; 32], 12)
+                    .with_steps_per_generation(20)
+                    .with_selection(HalfField(Side::East))
+                    .with_movement_conflict(MovementConflict::RandomWinner)
+                    .build().unwrap());
+                sim.init().unwrap();
+
+                for _ in 0..3 {
+                    sim.run_generation().unwrap();
+                }
+                for _ in 0..10 {
+                    sim.step();
+                }
+
+                sim.creatures()
+                    .iter()
+                    .map(|creature| (*creature.position(), creature.genome().clone()))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let single_threaded = run_with_threads(1);
+        assert_eq!(single_threaded, run_with_threads(2));
+        assert_eq!(single_threaded, run_with_threads(8));
+    }
+
+    #[test]
+    fn validate_config() {
+        let builder = || gen_config_builder(10, 10, 20, [0; 32], 4);
+
+        assert!(builder().build().is_ok());
+        assert_eq!(
+            gen_config_builder(0, 10, 0, [0; 32], 4).build().err(),
+            Some(SimulationConfigError::FieldTooSmall(0, 10))
+        );
+        assert_eq!(
+            builder().with_population(101).build().err(),
+            Some(SimulationConfigError::PopulationExceedsField(101, 100))
+        );
+        assert_eq!(builder().with_total_genes(0).build().err(), Some(SimulationConfigError::NoGenes));
+        assert_eq!(builder().with_steps_per_generation(0).build().err(), Some(SimulationConfigError::NoSteps));
+        assert_eq!(
+            builder().with_point_mutation(PointMutation::PerBit(1.5)).build().err(),
+            Some(SimulationConfigError::InvalidMutationRate(1.5))
+        );
+        assert_eq!(builder().with_internal_neurons(0).build().err(), Some(SimulationConfigError::NoInternalNeurons));
+        assert!(builder().with_internal_neurons(32).build().is_ok());
+        assert_eq!(
+            builder().with_internal_neurons(100).build().err(),
+            Some(SimulationConfigError::TooManyInternalNeurons(100, 32))
+        );
+        assert!(builder().with_internal_neurons(100).with_gene_format(GeneFormat::Wide).build().is_ok());
+        assert_eq!(
+            builder().with_internal_neurons(129).with_gene_format(GeneFormat::Wide).build().err(),
+            Some(SimulationConfigError::TooManyInternalNeurons(129, 128))
+        );
+        assert_eq!(builder().with_neuron_decay(1.2).build().err(), Some(SimulationConfigError::InvalidNeuronDecay(1.2)));
+
+        let length_mutation = LengthMutation { insertion_rate: 0.1, deletion_rate: 0.1, min_genes: 2, max_genes: 8 };
+        assert!(builder().with_length_mutation(length_mutation).build().is_ok());
+        assert_eq!(
+            builder().with_length_mutation(LengthMutation { max_genes: 3, ..length_mutation }).build().err(),
+            Some(SimulationConfigError::InvalidGenomeLength(2, 4, 3))
+        );
+        assert_eq!(
+            builder().with_length_mutation(LengthMutation { deletion_rate: -0.1, ..length_mutation }).build().err(),
+            Some(SimulationConfigError::InvalidMutationRate(-0.1))
+        );
+    }
+
+    fn gen_checkpoint_config() -> SimulationConfig {
+        gen_config_builder(30, 30, 120, [31; 32], 12)
+            .with_steps_per_generation(15)
+            .with_selection(HalfField(Side::West))
+            .with_movement_conflict(MovementConflict::RandomWinner)
+            .with_reproduction(Reproduction::Sexual {
+                crossover: Crossover::Uniform,
+                pairing: Pairing::GeneticSimilarity
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn run_steps(sim: &mut Simulation, total_steps: usize) {
+        for _ in 0..total_steps {
+            match sim.current_step() < sim.config().steps_per_generation {
+                true => sim.step(),
+                false => { sim.next_generation().unwrap(); }
+            }
+        }
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let mut uninterrupted = Simulation::new(gen_checkpoint_config());
+        uninterrupted.init().unwrap();
+        run_steps(&mut uninterrupted, 40);
+
+        let mut saved = vec![];
+        uninterrupted.save_checkpoint(&mut saved).unwrap();
+        let mut resumed = Simulation::load_checkpoint(gen_checkpoint_config(), &mut saved.as_slice()).unwrap();
+
+        assert_eq!(resumed.generation(), uninterrupted.generation());
+        assert_eq!(resumed.current_step(), uninterrupted.current_step());
+
+        run_steps(&mut uninterrupted, 50);
+        run_steps(&mut resumed, 50);
+
+        // Saving both again should give the exact same bytes
+        let (mut expected, mut actual) = (vec![], vec![]);
+        uninterrupted.save_checkpoint(&mut expected).unwrap();
+        resumed.save_checkpoint(&mut actual).unwrap();
+        assert_eq!(actual, expected);
+        assert_no_shared_cell(&resumed);
+    }
+
+    #[test]
+    fn reject_mismatching_checkpoint() {
+        let mut sim = Simulation::new(gen_checkpoint_config());
+        sim.init().unwrap();
+
+        let mut saved = vec![];
+        sim.save_checkpoint(&mut saved).unwrap();
+
+        let other_config = gen_config_builder(31, 30, 120, [31; 32], 12).build().unwrap();
+        assert!(matches!(
+            Simulation::load_checkpoint(other_config, &mut saved.as_slice()),
+            Err(CheckpointError::FieldMismatch(30, 30, 31, 30))
+        ));
 
-        println!("{:?}", sim.creatures.borrow()[0].position());
+        let other_config = gen_config_builder(30, 30, 120, [31; 32], 12).with_internal_neurons(2).build().unwrap();
+        assert!(matches!(
+            Simulation::load_checkpoint(other_config, &mut saved.as_slice()),
+            Err(CheckpointError::InternalNeuronMismatch(4, 2))
+        ));
 
-        assert_eq!(sim.is_position_occupied(&Vector2D::new(100, 100)), true);
-        assert_eq!(sim.is_position_occupied(&Vector2D::new(10, 10)), false);
+        saved.truncate(saved.len() - 1);
+        assert!(matches!(
+            Simulation::load_checkpoint(gen_checkpoint_config(), &mut saved.as_slice()),
+            Err(CheckpointError::Io(_))
+        ));
     }
 }
@@ -0,0 +1,128 @@
+use crate::vector2d::{Direction, Vector2D};
+
+
+// What happens at the edges of the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    // Edges are walls; nothing goes past them
+    Walled,
+    // Edges wrap around to the opposite side (a torus)
+    Toroidal,
+    // Running into an edge bounces back, like light off a mirror
+    Reflecting,
+}
+
+impl Topology {
+    // Move `pos` by `offset`, in a field of `width` x `height`
+    // Returns None if the result is outside the field (never happens on a torus)
+    // Reflecting edges behave like walls here; bouncing only applies to `step`
+    pub fn translate(self, pos: &Vector2D<usize>, offset: Vector2D<isize>, width: usize, height: usize) -> Option<Vector2D<usize>> {
+        match self {
+            Topology::Toroidal => Some(Vector2D::new(
+                wrap(pos.x, offset.x, width),
+                wrap(pos.y, offset.y, height)
+            )),
+
+            Topology::Walled | Topology::Reflecting => pos.checked_add_signed(offset)
+                .filter(|new_pos| new_pos.x < width && new_pos.y < height)
+        }
+    }
+
+    // The neighbouring cell of `pos` toward `direction`, and the direction actually taken
+    // On reflecting edges, the blocked axis is flipped; the direction only changes there
+    // Returns None if there's nowhere to go
+    pub fn step(self, pos: &Vector2D<usize>, direction: Direction, width: usize, height: usize) -> Option<(Vector2D<usize>, Direction)> {
+        if self != Topology::Reflecting {
+            return self.translate(pos, direction.offset(), width, height)
+                .map(|new_pos| (new_pos, direction));
+        }
+
+        let offset = direction.offset();
+        let dx = reflect(pos.x, offset.x, width);
+        let dy = reflect(pos.y, offset.y, height);
+        let new_direction = Direction::from_offset(dx, dy)?;
+
+        Some((pos.checked_add_signed(Vector2D::new(dx, dy))?, new_direction))
+    }
+
+    // Shortest offset going from `from` to `to`; on a torus, it might go across an edge
+    pub fn offset_between(self, from: &Vector2D<usize>, to: &Vector2D<usize>, width: usize, height: usize) -> Vector2D<isize> {
+        let dx = to.x as isize - from.x as isize;
+        let dy = to.y as isize - from.y as isize;
+
+        match self {
+            Topology::Toroidal => Vector2D::new(shortest_wrap(dx, width), shortest_wrap(dy, height)),
+            Topology::Walled | Topology::Reflecting => Vector2D::new(dx, dy)
+        }
+    }
+
+    pub fn distance_squared(self, a: &Vector2D<usize>, b: &Vector2D<usize>, width: usize, height: usize) -> usize {
+        let offset = self.offset_between(a, b, width, height);
+        (offset.x * offset.x + offset.y * offset.y) as usize
+    }
+}
+
+fn wrap(coord: usize, delta: isize, size: usize) -> usize {
+    (coord as isize + delta).rem_euclid(size as isize) as usize
+}
+
+fn shortest_wrap(delta: isize, size: usize) -> isize {
+    let size = size as isize;
+    let delta = delta.rem_euclid(size);
+    match delta > size / 2 {
+        true => delta - size,
+        false => delta
+    }
+}
+
+// Flip `delta` if it would leave the field; 0 if neither way fits (field is 1 cell wide)
+fn reflect(coord: usize, delta: isize, size: usize) -> isize {
+    let fits = |delta: isize| coord.checked_add_signed(delta).is_some_and(|c| c < size);
+
+    match (fits(delta), fits(-delta)) {
+        (true, _) => delta,
+        (false, true) => -delta,
+        (false, false) => 0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_over_edges() {
+        let corner = Vector2D::new(0, 0);
+
+        assert_eq!(Topology::Walled.step(&corner, Direction::NorthWest, 5, 4), None);
+        assert_eq!(Topology::Walled.step(&corner, Direction::SouthEast, 5, 4), Some((Vector2D::new(1, 1), Direction::SouthEast)));
+
+        assert_eq!(Topology::Toroidal.step(&corner, Direction::NorthWest, 5, 4), Some((Vector2D::new(4, 3), Direction::NorthWest)));
+        assert_eq!(
+            Topology::Toroidal.step(&Vector2D::new(4, 3), Direction::East, 5, 4),
+            Some((Vector2D::new(0, 3), Direction::East))
+        );
+
+        assert_eq!(Topology::Reflecting.step(&corner, Direction::NorthWest, 5, 4), Some((Vector2D::new(1, 1), Direction::SouthEast)));
+        assert_eq!(Topology::Reflecting.step(&corner, Direction::North, 5, 4), Some((Vector2D::new(0, 1), Direction::South)));
+        assert_eq!(
+            Topology::Reflecting.step(&Vector2D::new(2, 3), Direction::SouthWest, 5, 4),
+            Some((Vector2D::new(1, 2), Direction::NorthWest))
+        );
+        // Nowhere to bounce in a 1 cell wide field
+        assert_eq!(Topology::Reflecting.step(&corner, Direction::East, 1, 4), None);
+    }
+
+    #[test]
+    fn distance_across_edges() {
+        let a = Vector2D::new(0, 1);
+        let b = Vector2D::new(9, 8);
+
+        assert_eq!(Topology::Walled.offset_between(&a, &b, 10, 10), Vector2D::new(9, 7));
+        assert_eq!(Topology::Reflecting.distance_squared(&a, &b, 10, 10), 81 + 49);
+        assert_eq!(Topology::Toroidal.offset_between(&a, &b, 10, 10), Vector2D::new(-1, -3));
+        assert_eq!(Topology::Toroidal.offset_between(&b, &a, 10, 10), Vector2D::new(1, 3));
+        assert_eq!(Topology::Toroidal.distance_squared(&a, &b, 10, 10), 1 + 9);
+    }
+}
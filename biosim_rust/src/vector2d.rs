@@ -1,4 +1,6 @@
+use biosim_rust_macros::enum_from_id;
 use num::Num;
+use rand::Rng;
 
 use std::ops::{Add, Sub, Mul};
 
@@ -15,6 +17,16 @@ impl<T> Vector2D<T>
     }
 }
 
+impl Vector2D<usize> {
+    // None if either coordinate would go below 0
+    pub fn checked_add_signed(self, offset: Vector2D<isize>) -> Option<Self> {
+        Some(Self::new(
+            self.x.checked_add_signed(offset.x)?,
+            self.y.checked_add_signed(offset.y)?
+        ))
+    }
+}
+
 
 impl<T> Add for Vector2D<T> 
     where T: Num {
@@ -52,6 +64,62 @@ impl<T> Mul for Vector2D<T>
 }
 
 
+// The 8 compass directions, clockwise from North
+// y grows southward, same as the grid
+#[enum_from_id]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest
+}
+
+impl Direction {
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::from_id(rng.gen_range(0..TOTAL_DIRECTION_VARIANT)).unwrap()
+    }
+
+    // Only the sign of each component matters; (0, 0) has no direction
+    pub fn from_offset(dx: isize, dy: isize) -> Option<Self> {
+        match (dx.signum(), dy.signum()) {
+            (0, -1) => Some(Self::North),
+            (1, -1) => Some(Self::NorthEast),
+            (1, 0) => Some(Self::East),
+            (1, 1) => Some(Self::SouthEast),
+            (0, 1) => Some(Self::South),
+            (-1, 1) => Some(Self::SouthWest),
+            (-1, 0) => Some(Self::West),
+            (-1, -1) => Some(Self::NorthWest),
+            _ => None
+        }
+    }
+
+    pub fn offset(self) -> Vector2D<isize> {
+        match self {
+            Self::North => Vector2D::new(0, -1),
+            Self::NorthEast => Vector2D::new(1, -1),
+            Self::East => Vector2D::new(1, 0),
+            Self::SouthEast => Vector2D::new(1, 1),
+            Self::South => Vector2D::new(0, 1),
+            Self::SouthWest => Vector2D::new(-1, 1),
+            Self::West => Vector2D::new(-1, 0),
+            Self::NorthWest => Vector2D::new(-1, -1),
+        }
+    }
+
+    // Turn by 45 degree steps; positive is clockwise
+    pub fn rotate(self, steps: isize) -> Self {
+        let id = (self as isize + steps).rem_euclid(TOTAL_DIRECTION_VARIANT as isize);
+        Self::from_id(id as usize).unwrap()
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +147,31 @@ mod tests {
 
         assert_eq!(a*b, 62);
     }
+
+    #[test]
+    fn checked_add_signed_vector2d() {
+        let a = Vector2D::new(3_usize, 0_usize);
+
+        assert_eq!(a.checked_add_signed(Vector2D::new(-3, 2)), Some(Vector2D::new(0, 2)));
+        assert_eq!(a.checked_add_signed(Vector2D::new(-4, 2)), None);
+        assert_eq!(a.checked_add_signed(Vector2D::new(1, -1)), None);
+    }
+
+    #[test]
+    fn direction_from_offset() {
+        for id in 0..TOTAL_DIRECTION_VARIANT {
+            let direction = Direction::from_id(id).unwrap();
+            let offset = direction.offset();
+            assert_eq!(Direction::from_offset(offset.x * 3, offset.y * 3), Some(direction));
+        }
+        assert_eq!(Direction::from_offset(0, 0), None);
+    }
+
+    #[test]
+    fn rotate_direction() {
+        assert_eq!(Direction::North.rotate(2), Direction::East);
+        assert_eq!(Direction::North.rotate(-1), Direction::NorthWest);
+        assert_eq!(Direction::SouthWest.rotate(4), Direction::NorthEast);
+        assert_eq!(Direction::West.rotate(10), Direction::North);
+    }
 }